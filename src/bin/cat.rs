@@ -5,9 +5,10 @@ extern crate env_logger;
 extern crate structopt;
 
 use structopt::StructOpt;
-use std::io::{self, Write};
+use std::io::Write;
+use std::process;
 
-mod util;
+use rust_core_utils::util;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "cat", about = "concatenate files and print on the standard output")]
@@ -44,15 +45,18 @@ struct Opt {
     #[structopt(short = "T", long = "show-tabs")]
     show_tabs: bool,
 
-    /// (ignored)
+    /// write output as soon as it is read
     #[structopt(short = "u")]
-    ignored: bool,
+    unbuffered: bool,
 
     /// use ^ and M- notation, except for LFD and TAB
     #[structopt(short = "v", long = "show-nonprinting")]
     show_nonprinting: bool,
 
-    ///
+    /// transparently decompress gzip, zstd and xz compressed files
+    #[structopt(long)]
+    decompress: bool,
+
     #[structopt(name = "FILES")]
     files: Vec<String>,
     
@@ -76,17 +80,22 @@ impl Opt {
             self.show_ends = true;
         }
 
-        if self.files.len() <= 0 {
+        if self.files.is_empty() {
             self.files.push("-".to_string());
         }
         
     }
+
+    /// Whether lines come out just as they went in
+    fn is_plain(&self) -> bool {
+        !(self.number || self.number_nonempty || self.show_ends || self.show_tabs || self.squeeze_blank)
+    }
 }
 
-fn valid(line: String, mut blank: usize, squeeze: bool) -> (bool, usize) {
+fn valid(line: &[u8], mut blank: usize, squeeze: bool) -> (bool, usize) {
     let mut ret = true;
     if squeeze {
-        if line.len() <= 0 {
+        if line.is_empty() {
             blank +=1;
         } else{
             blank = 0;
@@ -95,68 +104,103 @@ fn valid(line: String, mut blank: usize, squeeze: bool) -> (bool, usize) {
             ret = false;
         }
     }
-    return (ret, blank);
+    (ret, blank)
 }
 
-fn format_line (mut line: String, show_ends: bool, show_tabs: bool, number: bool, line_count: usize) -> String {
+fn format_line (mut line: Vec<u8>, show_ends: bool, show_tabs: bool, number: Option<Vec<u8>>) -> Vec<u8> {
     if show_ends {
-        line.push('$')
+        line.push(b'$')
     }
     if show_tabs {
-        line = line.replace("\t", "^I");
+        let mut replaced = Vec::with_capacity(line.len());
+        for b in line {
+            match b {
+                b'\t' => replaced.extend_from_slice(b"^I"),
+                _ => replaced.push(b),
+            }
+        }
+        line = replaced;
     }
-    if number {
-        line = format!("\t{}  {}", line_count, line);
+    if let Some(mut numbered) = number {
+        numbered.append(&mut line);
+        line = numbered;
     }
     line
 }
 
 
+/// Reports what stopped reading `f` short, 1 when something did
+fn read_error(f: &mut util::file::FileDetail) -> i32 {
+    match f.take_read_error() {
+        Some(e) => {
+            eprintln!("cat: {}: {}", f.path(), util::error::describe(&e));
+            1
+        },
+        None => 0,
+    }
+}
+
 fn main()  {
     env_logger::init();
     let mut opt = Opt::from_args();
     opt.initialize();
     info!("Working with options => {:?}", opt);
     
+    let mut out = util::output::stdout(util::output::Buffering::pick(false, opt.unbuffered));
+    let mut exit_code = 0;
     let mut line_count = 0;
-    let blank_line_count = 0;
+    let mut blank_line_count = 0;
+    let numbering = util::number::Numbering::default();
     
-    for file in opt.files {
+    for file in opt.files.iter() {
         trace!("Processing file => {}", file);
         let mut f = util::file::new(file.clone());
-        match f.prepare(0){
-            Err(e) => {
-                info!("error found while preparing file {} => {}", file, e);
-                println!("cat: {}: {}", file, e);
-                continue
-            },
-            _ => Some(0),
+        f.set_decompress(opt.decompress);
+        if let Err(e) = f.prepare(0) {
+            info!("error found while preparing file {} => {}", file, e);
+            eprintln!("cat: {}: {}", file, util::error::describe(&e));
+            exit_code = 1;
+            continue
         };
 
-        loop {
-            let mut valid_line = false;
-            let mut line = match f.read_line() {
-                Some(line) => line,
-                None => break,
-            };
-            line.pop(); // ignoring the new line feed
-            let (valid_line, blank_line_count) = valid(line.clone(), blank_line_count, opt.squeeze_blank);
-            if valid_line {
-                line_count += 1;
-                println!("{}", format_line(line.clone(), opt.show_ends, opt.show_tabs, opt.number, line_count));
+        if opt.is_plain() {
+            if let Err(e) = f.copy_to(&mut out, None) {
+                error!("write error => {}", e);
             }
+            trace!("File copied => {}", file);
+            exit_code |= read_error(&mut f);
+            continue
         }
-        if !f.is_text() {
-            debug!("Non text file found, trying by chunk");
-            let size = 1024 * 60;
-            let mut writter = io::stdout();
-            match f.read_chunk(size) {
-                Some(b) => writter.write_all(&b),
-                _ => Ok(()),
-            };
+
+        // lines are handled as bytes, binary files are formatted the same way
+        while let Some(mut line) = f.read_line_bytes() {
+            let new_line = line.ends_with(b"\n");
+            if new_line {
+                line.pop();
+            }
+            let (valid_line, blank) = valid(&line, blank_line_count, opt.squeeze_blank);
+            blank_line_count = blank;
+            if valid_line {
+                let number = if opt.number_nonempty { !line.is_empty() } else { opt.number };
+                if number {
+                    line_count += 1;
+                }
+                let number = number.then(|| numbering.number(line_count));
+                let mut line = format_line(line, opt.show_ends && new_line, opt.show_tabs, number);
+                if new_line {
+                    line.push(b'\n');
+                }
+                if let Err(e) = out.write_all(&line) {
+                    error!("write error => {}", e);
+                }
+            }
         }
         
         trace!("File processed => {}", file);
+        exit_code |= read_error(&mut f);
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
     }
+    process::exit(exit_code);
 }
-
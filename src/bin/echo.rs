@@ -1,5 +1,10 @@
 extern crate structopt;
 
+use rust_core_utils::util;
+
+use std::io::{self, Write};
+use std::process;
+
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -17,37 +22,76 @@ struct Opt {
     #[structopt(short = "E")]
     disable_escape: bool,
 
-    ///
     #[structopt(name = "STRING")]
     strings: Vec<String>,
     
 }
 
-fn apply_escapes(line: String) -> String {
-    line
-        .replace("\\t", "\t")
-        .replace("\\n", "\n")
-        .replace("\\a", "\x07")
-        .replace("\\b", "\x08")
-        .replace("\\e", "\x1b")
-        .replace("\\f", "\x0c")
-        .replace("\\r", "\r")
-        .replace("\\v", "\x0b")
-        .replace("\\", "\\")
-        .split("\\c").next().unwrap().to_string()
+/// returns the escaped line and whether \c asked to stop producing output,
+/// escapes are read left to right so an escaped backslash never starts another
+fn apply_escapes(line: &str) -> (Vec<u8>, bool) {
+    let bytes = line.as_bytes();
+    let mut escaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            escaped.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        i += 2;
+        match bytes[i - 1] {
+            b'\\' => escaped.push(b'\\'),
+            b'a' => escaped.push(0x07),
+            b'b' => escaped.push(0x08),
+            b'c' => return (escaped, true),
+            b'e' => escaped.push(0x1b),
+            b'f' => escaped.push(0x0c),
+            b'n' => escaped.push(b'\n'),
+            b'r' => escaped.push(b'\r'),
+            b't' => escaped.push(b'\t'),
+            b'v' => escaped.push(0x0b),
+            // up to three octal digits after the 0
+            b'0' => {
+                let digits = bytes[i..].iter().take(3).take_while(|b| (b'0'..=b'7').contains(b)).count();
+                let value = bytes[i..i + digits].iter().fold(0u32, |v, b| v * 8 + (b - b'0') as u32);
+                escaped.push(value as u8);
+                i += digits;
+            },
+            // up to two hex digits, none at all leaves \x as it is
+            b'x' => {
+                let digits = bytes[i..].iter().take(2).take_while(|b| b.is_ascii_hexdigit()).count();
+                if digits == 0 {
+                    escaped.extend_from_slice(b"\\x");
+                    continue;
+                }
+                let hex = std::str::from_utf8(&bytes[i..i + digits]).unwrap();
+                escaped.push(u8::from_str_radix(hex, 16).unwrap());
+                i += digits;
+            },
+            other => escaped.extend_from_slice(&[b'\\', other]),
+        }
+    }
+    (escaped, false)
 }
 
 fn main() {
     let opt = Opt::from_args();
-    let mut output_line = opt.strings.join(" ");
+    let mut output_line = opt.strings.join(" ").into_bytes();
+    let mut stop = false;
 
-    if !opt.disable_escape {
-        output_line = apply_escapes(output_line);
+    if opt.enable_escape && !opt.disable_escape {
+        let (escaped, c) = apply_escapes(&String::from_utf8_lossy(&output_line));
+        output_line = escaped;
+        stop = c;
     }
 
-    print!("{}", output_line);
-    if !opt.disable_new_line {
-        print!("\n")
+    let mut out = io::stdout();
+    if !opt.disable_new_line && !stop {
+        output_line.push(b'\n');
+    }
+    if let Err(e) = out.write_all(&output_line).and_then(|_| out.flush()) {
+        eprintln!("echo: write error: {}", util::error::describe(&e));
+        process::exit(1);
     }
 }
-
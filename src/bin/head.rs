@@ -2,10 +2,10 @@
 extern crate log;
 extern crate env_logger;
 
-#[macro_use]
-mod util;
+use rust_core_utils::util;
 
 use std::io::{self, Write};
+use std::process;
 
 use structopt::StructOpt;
 
@@ -13,12 +13,12 @@ use structopt::StructOpt;
 #[structopt(name = "head", about = "output the first part of files")]
 struct Opt {
     /// print the first K bytes of each file; with the leading '-', print all but the last K bytes of each file
-    #[structopt(short = "c", long = "bytes", default_value="0")]
-    bytes: usize,
+    #[structopt(short = "c", long = "bytes", raw(overrides_with_all = r#"&["bytes", "lines"]"#))]
+    bytes: Option<String>,
 
     /// print the first K lines instead of the first 10; with the leading '-', print all but the last K lines of each file
-    #[structopt(short = "n", long = "lines", default_value="10")]
-    lines: usize,
+    #[structopt(short = "n", long = "lines", default_value="10", raw(overrides_with_all = r#"&["bytes", "lines"]"#))]
+    lines: String,
     
     /// never print headers giving file names
     #[structopt(long, short)]
@@ -32,7 +32,10 @@ struct Opt {
     #[structopt(long, short)]
     verbose: bool,
 
-    ///
+    /// transparently decompress gzip, zstd and xz compressed files
+    #[structopt(long)]
+    decompress: bool,
+
     #[structopt(name = "FILES")]
     files: Vec<String>,
 
@@ -40,58 +43,139 @@ struct Opt {
 
 impl Opt {
     fn initialize(&mut self) {
-        self.silent = !self.verbose;
-        self.quiet = self.silent;
-        if self.files.len() <= 0 {
+        self.quiet = self.quiet || self.silent;
+        if self.files.is_empty() {
             self.files.push("-".to_string());
         }
     }
+
+    /// Headers are printed for more than one file, unless told otherwise
+    fn headers(&self) -> bool {
+        self.verbose || (self.files.len() > 1 && !self.quiet)
+    }
+
+    /// Counts may carry a multiplier suffix, errors read as gnu's
+    fn limit(&self) -> Result<Limit, String> {
+        let (count, what) = match &self.bytes {
+            Some(bytes) => (bytes, "bytes"),
+            None => (&self.lines, "lines"),
+        };
+        let parsed = util::size::parse(count).map_err(|e| match e.reason() {
+            Some(reason) => format!("invalid number of {}: '{}': {}", what, count, reason),
+            None => format!("invalid number of {}: '{}'", what, count),
+        })?;
+        match self.bytes {
+            Some(_) => Ok(Limit::Bytes(parsed)),
+            None => Ok(Limit::Lines(parsed)),
+        }
+    }
 }
 
-fn readprint_chunk(file: &mut util::file::FileDetail, buffer_size: usize) {
-    let b = file.read_chunk(buffer_size);
-    if b.is_some(){
-        let mut writter = io::stdout();
-        writter.write_all(&b.unwrap());
-    }        
+/// How much of each file is printed, counted again from the start of every file
+#[derive(Debug, Clone, Copy)]
+enum Limit {
+    Lines(u64),
+    Bytes(u64),
+}
+
+fn display_name(file: &str) -> &str {
+    match file {
+        "-" => "standard input",
+        _ => file,
+    }
+}
+
+/// Streams the first `bytes` bytes a buffer at a time, however large the count
+fn readprint_chunk(file: &mut util::file::FileDetail, bytes: u64, writter: &mut dyn Write) {
+    if let Err(e) = file.copy_to(writter, Some(bytes)) {
+        error!("write error => {}", e);
+    }
+}
+
+/// Prints the first `lines` lines, lines that aren't text are copied as they are
+fn readprint_lines(file: &mut util::file::FileDetail, lines: u64, writter: &mut dyn Write) {
+    let mut printed: u64 = 0;
+    while printed < lines {
+        match file.read_line() {
+            Some(line) => if let Err(e) = writter.write_all(line.as_bytes()) {
+                error!("write error => {}", e);
+                break;
+            },
+            // the line that isn't text is copied as it is, text reading goes on after it
+            None if !file.is_text() => match file.copy_lines_to(writter, 1) {
+                Ok(0) => break,
+                Ok(_) => {},
+                Err(e) => {
+                    error!("write error => {}", e);
+                    break;
+                },
+            },
+            None => break,
+        }
+        printed += 1;
+    }
+}
+
+/// Prints the start of `file` behind its header, the first header printed
+/// isn't preceded by a blank line. False when the file couldn't be read.
+fn head_file(file: &str, opt: &Opt, limit: Limit, first: &mut bool, out: &mut dyn Write) -> bool {
+    let mut f = util::file::new(file.to_string());
+    f.set_decompress(opt.decompress);
+    if let Err(e) = f.prepare(0) {
+        info!("error found while preparing file {} => {}", file, e);
+        match e.kind() {
+            io::ErrorKind::IsADirectory => eprintln!("head: error reading '{}': {}", file, util::error::describe(&e)),
+            _ => eprintln!("head: cannot open '{}' for reading: {}", file, util::error::describe(&e)),
+        }
+        return false;
+    };
+    if opt.headers() {
+        let separator = if *first { "" } else { "\n" };
+        if let Err(e) = writeln!(out, "{}==> {} <==", separator, display_name(file)) {
+            error!("write error => {}", e);
+        }
+        *first = false;
+    }
+    match limit {
+        Limit::Bytes(bytes) => readprint_chunk(&mut f, bytes, out),
+        Limit::Lines(lines) => readprint_lines(&mut f, lines, out),
+    }
+    if let Some(e) = f.take_read_error() {
+        eprintln!("head: error reading '{}': {}", file, util::error::describe(&e));
+        return false;
+    }
+    true
 }
 
 fn main() {
-    let mut line_number = 0;
-    let mut opt = Opt::from_args();
+    let args = match util::obsolete::head(std::env::args_os().collect()) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("head: {}", e);
+            eprintln!("Try 'head --help' for more information.");
+            process::exit(1);
+        },
+    };
+    let mut opt = Opt::from_iter(args);
     opt.initialize();
     env_logger::init();
-    
-    for file in opt.files {
-        let mut f = util::file::new(file.clone());
-        if opt.verbose {
-            println!("==> {} <==", file);
-        }
-        
-        match f.prepare(0){
-            Err(e) => {
-                info!("error found while preparing file {} => {}", file, e);
-                println!("head: cannot open '{}' for reading: {}", file, e);
-                continue
-            },
-            _ => Some(0),
-        };
-        if opt.bytes > 0 {
-            readprint_chunk(&mut f, opt.bytes);
-        } else {
-            loop {
-                match f.read_line() {
-                    Some(line) => print!("{}", line),
-                    None => break,
-                }
-                if !f.is_text() { break; }
-                line_number += 1;
-                if line_number > opt.lines { break; }
-            }
-            if !f.is_text() {
-                let size = 1024 * 60;
-                readprint_chunk(&mut f, size);
-            }
+    let limit = match opt.limit() {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("head: {}", e);
+            process::exit(1);
+        },
+    };
+    let mut exit_code = 0;
+    let mut out = util::output::stdout(util::output::Buffering::pick(false, false));
+    let mut first = true;
+    for file in opt.files.iter() {
+        if !head_file(file, &opt, limit, &mut first, &mut out) {
+            exit_code = 1;
         }
     }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    process::exit(exit_code);
 }
@@ -0,0 +1,311 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+use rust_core_utils::util;
+
+use util::number::{Align, Numbering};
+
+use std::io::Write;
+use std::process;
+
+use regex::bytes::Regex;
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "nl", about = "Write each FILE to standard output, with line numbers added.
+With no FILE, or when FILE is -, read standard input.
+
+STYLE is one of:
+  a      number all lines
+  t      number only nonempty lines
+  n      number no lines
+  pREGEX number only lines that contain a match for REGEX, written in
+         Rust regex syntax rather than as a basic regular expression
+
+FORMAT is one of:
+  ln     left justified, no leading zeros
+  rn     right justified, no leading zeros
+  rz     right justified, leading zeros
+")]
+struct Opt {
+    /// use STYLE for numbering body lines
+    #[structopt(short = "b", long = "body-numbering", name = "STYLE", default_value = "t")]
+    body_numbering: String,
+
+    /// use CC for logical page delimiters
+    #[structopt(short = "d", long = "section-delimiter", name = "CC", default_value = "\\:")]
+    section_delimiter: String,
+
+    /// use STYLE for numbering footer lines
+    #[structopt(short = "f", long = "footer-numbering", name = "FOOTER_STYLE", default_value = "n")]
+    footer_numbering: String,
+
+    /// use STYLE for numbering header lines
+    #[structopt(short = "h", long = "header-numbering", name = "HEADER_STYLE", default_value = "n")]
+    header_numbering: String,
+
+    /// line number increment at each line
+    #[structopt(short = "i", long = "line-increment", name = "NUMBER", default_value = "1", raw(allow_hyphen_values = "true"))]
+    line_increment: String,
+
+    /// group of NUMBER empty lines counted as one
+    #[structopt(short = "l", long = "join-blank-lines", name = "BLANK_LINES", default_value = "1")]
+    join_blank_lines: String,
+
+    /// insert line numbers according to FORMAT
+    #[structopt(short = "n", long = "number-format", name = "FORMAT", default_value = "rn")]
+    number_format: String,
+
+    /// do not reset line numbers for each section
+    #[structopt(short = "p", long = "no-renumber")]
+    no_renumber: bool,
+
+    /// add STRING after (possible) line number
+    #[structopt(short = "s", long = "number-separator", name = "STRING", default_value = "\t")]
+    number_separator: String,
+
+    /// first line number for each section
+    #[structopt(short = "v", long = "starting-line-number", name = "START", default_value = "1", raw(allow_hyphen_values = "true"))]
+    starting_line_number: String,
+
+    /// use NUMBER columns for line numbers
+    #[structopt(short = "w", long = "number-width", name = "WIDTH", default_value = "6")]
+    number_width: String,
+
+    #[structopt(name = "FILES")]
+    files: Vec<String>,
+
+}
+
+/// Which lines of a section get a number
+#[derive(Debug)]
+enum Style {
+    All,
+    NonEmpty,
+    Nothing,
+    Matching(Regex),
+}
+
+impl Style {
+    /// Parses a-t-n-pREGEX, None when the style is none of them
+    fn parse(style: &str) -> Option<Result<Style, String>> {
+        match style {
+            "a" => Some(Ok(Style::All)),
+            "t" => Some(Ok(Style::NonEmpty)),
+            "n" => Some(Ok(Style::Nothing)),
+            _ => style.strip_prefix('p').map(|pattern| {
+                Regex::new(pattern).map(Style::Matching).map_err(|e| {
+                    info!("invalid numbering regex {} => {}", pattern, e);
+                    "Invalid regular expression".to_string()
+                })
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Header,
+    Body,
+    Footer,
+}
+
+/// What one of the options comes down to
+#[derive(Debug)]
+struct Settings {
+    styles: [Style; 3],
+    /// the lines starting each section
+    delimiters: Vec<(Vec<u8>, Section)>,
+    numbering: Numbering,
+    start: i64,
+    increment: i64,
+    blank_lines: u64,
+    renumber: bool,
+}
+
+/// A number option as gnu reads them, the message telling what's wrong otherwise
+fn number<T: std::str::FromStr + PartialOrd>(value: &str, what: &str, min: T) -> Result<T, String> {
+    let digits = value.trim_start();
+    let numeric = digits.strip_prefix(['-', '+']).unwrap_or(digits);
+    if numeric.is_empty() || !numeric.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("invalid {}: '{}'", what, value));
+    }
+    match digits.parse::<T>() {
+        Ok(n) if n >= min => Ok(n),
+        _ => Err(format!("invalid {}: '{}': Numerical result out of range", what, value)),
+    }
+}
+
+impl Opt {
+    fn initialize(&mut self) {
+        if self.files.is_empty() {
+            self.files.push("-".to_string());
+        }
+    }
+
+    fn settings(&self) -> Result<Settings, String> {
+        let start = number(&self.starting_line_number, "starting line number", i64::MIN)?;
+        let increment = number(&self.line_increment, "line number increment", i64::MIN)?;
+        let blank_lines = number(&self.join_blank_lines, "line number of blank lines", 1)?;
+        let width = number(&self.number_width, "line number field width", 1)?;
+        if width > i32::MAX as usize {
+            return Err(format!("invalid line number field width: '{}': Numerical result out of range", self.number_width));
+        }
+        let style = |style: &str, section: &str| {
+            Style::parse(style).unwrap_or_else(|| {
+                Err(format!("invalid {} numbering style: '{}'\nTry 'nl --help' for more information.", section, style))
+            })
+        };
+        let styles = [
+            style(&self.header_numbering, "header")?,
+            style(&self.body_numbering, "body")?,
+            style(&self.footer_numbering, "footer")?,
+        ];
+        let align = Align::parse(&self.number_format).ok_or_else(|| {
+            format!("invalid line numbering format: '{}'\nTry 'nl --help' for more information.", self.number_format)
+        })?;
+        // one or two characters replace the start of \: as posix has it, longer ones all of it
+        let mut delimiter = self.section_delimiter.as_bytes().to_vec();
+        if delimiter.len() == 1 {
+            delimiter.push(b':');
+        }
+        // headers and bodies repeat the footer delimiter three and two times, an empty one turns sections off
+        let delimiters = match delimiter.is_empty() {
+            true => vec![],
+            false => vec![(delimiter.repeat(3), Section::Header), (delimiter.repeat(2), Section::Body), (delimiter, Section::Footer)],
+        };
+        Ok(Settings {
+            styles,
+            delimiters,
+            numbering: Numbering { align, width, separator: self.number_separator.as_bytes().to_vec() },
+            start,
+            increment,
+            blank_lines,
+            renumber: !self.no_renumber,
+        })
+    }
+}
+
+/// Numbers lines across every file, sections and numbers going on from one file to the next
+struct Numberer {
+    settings: Settings,
+    section: Section,
+    /// None once the last number given couldn't be followed by another
+    next: Option<i64>,
+    /// empty lines in a row not numbered yet, for -l
+    blank_lines: u64,
+}
+
+impl Numberer {
+    fn new(settings: Settings) -> Numberer {
+        let next = Some(settings.start);
+        Numberer { settings, section: Section::Body, next, blank_lines: 0 }
+    }
+
+    /// The section a delimiter line starts, None for lines of text
+    fn delimiter(&self, line: &[u8]) -> Option<Section> {
+        self.settings.delimiters.iter().find(|(d, _)| d == line).map(|&(_, section)| section)
+    }
+
+    fn numbered(&mut self, line: &[u8]) -> bool {
+        match &self.settings.styles[self.section as usize] {
+            Style::All if self.settings.blank_lines > 1 => {
+                if !line.is_empty() {
+                    self.blank_lines = 0;
+                    return true;
+                }
+                self.blank_lines += 1;
+                if self.blank_lines == self.settings.blank_lines {
+                    self.blank_lines = 0;
+                }
+                self.blank_lines == 0
+            },
+            Style::All => true,
+            Style::NonEmpty => !line.is_empty(),
+            Style::Nothing => false,
+            Style::Matching(r) => r.is_match(line),
+        }
+    }
+
+    /// Writes `line`, without its new line, numbered or not as its section has it
+    fn write(&mut self, line: &[u8], out: &mut dyn Write) -> Result<(), String> {
+        if let Some(section) = self.delimiter(line) {
+            self.section = section;
+            if self.settings.renumber {
+                self.next = Some(self.settings.start);
+            }
+            write(out, b"\n");
+            return Ok(());
+        }
+        let prefix = match self.numbered(line) {
+            true => {
+                let number = self.next.ok_or_else(|| "line number overflow".to_string())?;
+                self.next = number.checked_add(self.settings.increment);
+                self.settings.numbering.number(number)
+            },
+            false => self.settings.numbering.blank(),
+        };
+        write(out, &prefix);
+        write(out, line);
+        write(out, b"\n");
+        Ok(())
+    }
+}
+
+fn write(out: &mut dyn Write, data: &[u8]) {
+    if let Err(e) = out.write_all(data) {
+        error!("write error => {}", e);
+    }
+}
+
+/// Numbers the lines of `file`, a last line without a new line gets one.
+/// False when the file couldn't be read, an error when numbering can't go on.
+fn nl_file(file: &str, numberer: &mut Numberer, out: &mut dyn Write) -> Result<bool, String> {
+    let mut f = util::file::new(file.to_string());
+    if let Err(e) = f.prepare(0) {
+        info!("error found while preparing file {} => {}", file, e);
+        eprintln!("nl: {}: {}", file, util::error::describe(&e));
+        return Ok(false);
+    }
+    while let Some(mut line) = f.read_line_bytes() {
+        if line.ends_with(b"\n") {
+            line.pop();
+        }
+        numberer.write(&line, out)?;
+    }
+    Ok(true)
+}
+
+fn main() {
+    let mut opt = Opt::from_args();
+    opt.initialize();
+    env_logger::init();
+    let settings = match opt.settings() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("nl: {}", e);
+            process::exit(1);
+        },
+    };
+    let mut numberer = Numberer::new(settings);
+    let mut exit_code = 0;
+    let mut out = util::output::stdout(util::output::Buffering::pick(false, false));
+    for file in opt.files.iter() {
+        match nl_file(file, &mut numberer, &mut out) {
+            Ok(true) => {},
+            Ok(false) => exit_code = 1,
+            Err(e) => {
+                if let Err(e) = out.flush() {
+                    error!("write error => {}", e);
+                }
+                eprintln!("nl: {}", e);
+                process::exit(1);
+            },
+        }
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    process::exit(exit_code);
+}
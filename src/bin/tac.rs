@@ -0,0 +1,98 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+use rust_core_utils::util;
+
+use util::reverse::{Reverse, Separator};
+
+use std::io::{self, Write};
+use std::process;
+
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "tac", about = "Write each FILE to standard output, last line first.
+With no FILE, or when FILE is -, read standard input.
+")]
+struct Opt {
+    /// attach the separator before instead of after
+    #[structopt(short = "b", long = "before")]
+    before: bool,
+
+    /// interpret the separator as a regular expression
+    #[structopt(short = "r", long = "regex")]
+    regex: bool,
+
+    /// use STRING as the separator instead of newline
+    #[structopt(short = "s", long = "separator", name = "STRING")]
+    separator: Option<String>,
+
+    #[structopt(name = "FILES")]
+    files: Vec<String>,
+
+}
+
+impl Opt {
+    fn initialize(&mut self) {
+        if self.files.is_empty() {
+            self.files.push("-".to_string());
+        }
+    }
+
+    fn reverse(&self) -> Result<Reverse, String> {
+        let separator = self.separator.as_deref().unwrap_or("\n");
+        let separator = match self.regex {
+            true => Separator::regex(separator).map_err(|e| {
+                info!("invalid separator {} => {}", separator, e);
+                "Invalid regular expression".to_string()
+            })?,
+            false => Separator::text(separator),
+        };
+        Ok(Reverse { separator, before: self.before })
+    }
+}
+
+/// Writes `file` last record first, stdin and pipes are spilled to a
+/// temporary file first. False when the file couldn't be read.
+fn tac_file(file: &str, reverse: &Reverse, out: &mut dyn Write) -> bool {
+    let mut f = util::file::new(file.to_string());
+    if let Err(e) = f.prepare(0) {
+        info!("error found while preparing file {} => {}", file, e);
+        match e.kind() {
+            io::ErrorKind::IsADirectory => eprintln!("tac: {}: read error: {}", file, util::error::describe(&e)),
+            _ => eprintln!("tac: failed to open '{}' for reading: {}", file, util::error::describe(&e)),
+        }
+        return false;
+    }
+    let written = f.spill().and_then(|_| f.opened_len()).and_then(|len| reverse.write(&f, 0, len, None, out));
+    if let Err(e) = written {
+        eprintln!("tac: {}: read error: {}", file, util::error::describe(&e));
+        return false;
+    }
+    true
+}
+
+fn main() {
+    let mut opt = Opt::from_args();
+    opt.initialize();
+    env_logger::init();
+    let reverse = match opt.reverse() {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("tac: {}", e);
+            process::exit(1);
+        },
+    };
+    let mut exit_code = 0;
+    let mut out = util::output::stdout(util::output::Buffering::pick(false, false));
+    for file in opt.files.iter() {
+        if !tac_file(file, &reverse, &mut out) {
+            exit_code = 1;
+        }
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    process::exit(exit_code);
+}
@@ -2,14 +2,41 @@
 extern crate log;
 extern crate env_logger;
 
-#[macro_use]
-mod util;
+use rust_core_utils::util;
+use util::checkpoint::Registry;
+use util::discovery::{Discovery, is_glob};
+use util::file::Stamp;
 use util::file_read_strategy::{FileReadStrategy};
+use util::output::{Buffering, Output};
+use util::reverse::{Reverse, Separator};
+use util::stdin;
+use util::timestamp::{Place, Window};
 
-use std::io::{self, Write};
+use regex::bytes::Regex;
+use serde::Serialize;
+use crossbeam::channel::TrySendError;
 use structopt::StructOpt;
+use std::collections::BTreeMap;
 use std::{thread, time};
-use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Bytes read at once when output starts at a given byte
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks read from a file each pass while following, a file written
+/// faster than it is read would keep every other file waiting otherwise
+const CHUNKS_PER_PASS: usize = 1;
+
+/// What grep colors its matches with
+const HIGHLIGHT: &str = "\x1b[01;31m\x1b[K";
+const HIGHLIGHT_END: &str = "\x1b[m\x1b[K";
+
+/// Bytes kept from the end of what was read to notice in place rewrites
+const FINGERPRINT_SIZE: usize = 512;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "tail", about = r"Print the last 10 lines of each FILE to standard output.
@@ -18,18 +45,25 @@ With no FILE, or when FILE is -, read standard input.
 ")]
 struct Opt {
     /// output the last K bytes; or use -c +K to output bytes starting with the Kth of each file
-    #[structopt(short = "c", long = "bytes", default_value="0")]
-    bytes: String,
+    #[structopt(short = "c", long = "bytes", raw(overrides_with_all = r#"&["bytes", "lines"]"#))]
+    bytes: Option<String>,
+
+    /// output appended data as the file grows, same as --follow=descriptor
+    #[structopt(short = "f")]
+    follow: bool,
 
     /// output appended data as the file grows;
     ///  an absent option argument means 'descriptor'
-    ///  same as --follow=name --retry
-    #[structopt(short, long,)]
-    follow: bool,
+    #[structopt(long = "follow", name = "HOW", raw(require_equals = "true", possible_values = r#"&["descriptor", "name"]"#))]
+    follow_how: Option<Option<String>>,
+
+    /// same as --follow=name --retry
+    #[structopt(short = "F")]
+    follow_name_retry: bool,
                
     /// output the last K lines or use -n +K to output starting with the Kth
-    #[structopt(short = "n", long = "lines", default_value="0")]
-    lines: String,
+    #[structopt(short = "n", long = "lines", raw(overrides_with_all = r#"&["bytes", "lines"]"#))]
+    lines: Option<String>,
 
     /// with --follow=name, reopen a FILE which has not
     ///  changed size after N iterations
@@ -37,6 +71,7 @@ struct Opt {
     ///  (this is the usual case of rotated log files);
     ///  with inotify, this option is rarely useful
     #[structopt(long = "max-unchanged-stats", default_value="5")]
+    #[allow(dead_code)] // files are reopened on every iteration anyway
     max_unchanged_stats: usize,
     
     /// with -f, terminate after process ID, PID dies
@@ -46,6 +81,12 @@ struct Opt {
     /// never print headers giving file names
     #[structopt(long, short)]
     quiet: bool,
+
+    /// output the lines last first, all of them unless -n or -c
+    /// tell how many lines or which bytes of the file to reverse
+    #[structopt(short = "r", long = "reverse")]
+    reverse: bool,
+
     /// same as --quiet
     #[structopt(long)]
     silent: bool,
@@ -60,11 +101,90 @@ struct Opt {
     #[structopt(short = "s", long = "sleep-interval", default_value="1.0")]
     sleep: f64,
 
+    /// with -f, look at the files every sleep interval instead of
+    ///  waiting on inotify, as done anyway on network filesystems
+    #[structopt(long = "disable-inotify", raw(alias = r#""-disable-inotify""#))]
+    disable_inotify: bool,
+
     /// always output headers giving file names
     #[structopt(long, short)]
     verbose: bool,
 
-    ///
+    /// transparently decompress gzip, zstd and xz compressed files
+    #[structopt(long)]
+    decompress: bool,
+
+    /// record where reading stopped in FILE and resume from there on
+    ///  the next run, files replaced or truncated since are read as usual
+    #[structopt(long = "resume-state", name = "FILE", parse(from_os_str))]
+    resume_state: Option<PathBuf>,
+
+    /// write text as read, or json: one object per line giving its
+    ///  file, offset, line_number, inode and the time it was received_at
+    #[structopt(long, name = "OUTPUT", default_value = "text", raw(value_name = r#""FORMAT""#, possible_values = r#"&["text", "json"]"#))]
+    output: String,
+
+    /// output at most N lines a second of each file, telling how many
+    ///  were left out once lines are let through again
+    #[structopt(long = "max-lines-per-sec", name = "N")]
+    max_lines_per_sec: Option<u64>,
+
+    /// when output can't keep up: block reading until it does,
+    ///  drop-oldest to drop the lines waiting the longest, or
+    ///  drop-newest to drop the lines just read
+    #[structopt(long, name = "POLICY", default_value = "block", raw(possible_values = r#"&["block", "drop-oldest", "drop-newest"]"#))]
+    overflow: String,
+
+    /// start each line with the name of its file instead of printing
+    ///  headers, FORMAT is a comma separated list of: basename to drop
+    ///  the directories, color to color each file apart, time to add
+    ///  the time the line was read
+    #[structopt(long, name = "FORMAT", raw(require_equals = "true"))]
+    prefix: Option<Option<String>>,
+
+    /// write each line as soon as it is complete, the default on terminals
+    #[structopt(long = "line-buffered")]
+    line_buffered: bool,
+
+    /// write output as soon as it is read
+    #[structopt(long)]
+    unbuffered: bool,
+
+    /// with -f, also follow the files of DIR, picking up files created
+    ///  later and leaving deleted ones; quoted patterns given as FILES
+    ///  are looked up again the same way
+    #[structopt(long = "watch-dir", name = "DIR", parse(from_os_str), raw(number_of_values = "1"))]
+    watch_dir: Vec<PathBuf>,
+
+    /// output the lines stamped at or after TIME, looking the first one up
+    ///  in the file rather than reading up to it; TIME is a timestamp as
+    ///  found in logs, a date, or a duration back from now such as 10m or 2h
+    #[structopt(long, name = "TIME")]
+    since: Option<String>,
+
+    /// output the lines stamped up to TIME, which is given as for --since;
+    ///  lines without a timestamp go along with the line before them
+    #[structopt(long, name = "UNTIL", raw(value_name = r#""TIME""#))]
+    until: Option<String>,
+
+    /// output only the lines matching REGEX, repeat it to output
+    ///  the lines matching any of them
+    #[structopt(long = "match", name = "REGEX", raw(number_of_values = "1"))]
+    matches: Vec<String>,
+
+    /// leave out the lines matching REGEX, it can be repeated
+    #[structopt(long = "exclude", name = "EXCLUDE", raw(number_of_values = "1", value_name = r#""REGEX""#))]
+    excludes: Vec<String>,
+
+    /// highlight what --match matched: always, never,
+    ///  or auto to highlight on terminals only
+    #[structopt(long, name = "WHEN", default_value = "auto", raw(possible_values = r#"&["auto", "always", "never"]"#))]
+    color: String,
+
+    /// only the files of --watch-dir whose names match GLOB
+    #[structopt(long, name = "GLOB", default_value = "*")]
+    pattern: String,
+
     #[structopt(name = "FILES")]
     files: Vec<String>,
 
@@ -72,279 +192,1264 @@ struct Opt {
 
 impl Opt {
     fn initialize(&mut self) {
-        self.silent = !self.verbose;
-        self.quiet = self.silent;
-        if self.files.len() <= 0 {
+        self.quiet = self.quiet || self.silent;
+        if self.files.is_empty() && self.watch_dir.is_empty() {
             self.files.push("-".to_string());
-            self.follow = true;
         }
-        if self.follow {
+        if self.follow_name_retry {
+            self.follow_how = Some(Some("name".to_string()));
             self.retry = true;
         }
-        if self.files.len() < 1 {
+        if self.follow_how.is_some() {
+            self.follow = true;
+        }
+        if self.retry && !self.follow {
+            eprintln!("tail: warning: --retry ignored; --retry is useful only when following");
+            self.retry = false;
+        } else if self.retry && !self.by_name() {
+            eprintln!("tail: warning: --retry only effective for the initial open");
+        }
+        let discovers = !self.watch_dir.is_empty() || self.files.iter().any(|f| is_glob(f));
+        if self.files.len() < 2 && !discovers && !self.verbose {
             self.quiet = true;
         }
+        if self.verbose {
+            self.quiet = false;
+        }
+    }
+
+    /// The option given along with -r that doesn't go with it, lines
+    /// are only looked at once the whole file has been read
+    fn reverse_conflict(&self) -> Option<&'static str> {
+        let conflicts = [
+            (self.follow, "--follow"),
+            (self.since.is_some(), "--since"),
+            (self.until.is_some(), "--until"),
+            (!self.matches.is_empty(), "--match"),
+            (!self.excludes.is_empty(), "--exclude"),
+            (self.output == "json", "--output=json"),
+            (self.max_lines_per_sec.is_some(), "--max-lines-per-sec"),
+            (self.prefix.is_some(), "--prefix"),
+            (self.resume_state.is_some(), "--resume-state"),
+            (!self.watch_dir.is_empty(), "--watch-dir"),
+        ];
+        conflicts.iter().find(|(given, _)| *given).map(|(_, option)| *option)
+    }
+
+    /// Whether files are followed by name, reopening them when they are
+    /// replaced, rather than by descriptor
+    fn by_name(&self) -> bool {
+        self.follow_how.as_ref().is_some_and(|how| how.as_deref() == Some("name"))
     }
 }
 
 #[derive(Debug)]
 struct Line {
     pub from_file: Option<String>,
-    pub content: String,
+    pub content: Vec<u8>,
+    /// where content starts in the file
+    pub offset: u64,
+    /// lines before content in the file, None when reading didn't start at the top
+    pub line_number: Option<u64>,
+    pub inode: Option<u64>,
 }
 
+/// A place in a file, the offset of a byte and how many lines come before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cursor {
+    pub offset: u64,
+    /// None once reading started somewhere lines weren't counted up to
+    pub line: Option<u64>,
+}
+
+impl Cursor {
+    fn at(offset: u64) -> Cursor {
+        Cursor { offset, line: if offset == 0 { Some(0) } else { None } }
+    }
+
+    /// Where `content` read from here ends
+    fn after(self, content: &[u8]) -> Cursor {
+        Cursor {
+            offset: self.offset + content.len() as u64,
+            line: self.line.map(|n| n + memchr::memchr_iter(b'\n', content).count() as u64),
+        }
+    }
+}
+
+/// How --prefix attributes each line to its file
+#[derive(Debug, Default, PartialEq)]
+struct Prefix {
+    pub basename: bool,
+    pub color: bool,
+    pub time: bool,
+}
+
+/// Colors handed to files in the order they show up
+const COLORS: &[&str] = &["\x1b[32m", "\x1b[33m", "\x1b[34m", "\x1b[35m", "\x1b[36m", "\x1b[31m"];
+const COLOR_RESET: &str = "\x1b[0m";
+
+impl Prefix {
+    pub fn parse(format: Option<&str>) -> Result<Prefix, String> {
+        let mut prefix = Prefix::default();
+        for word in format.unwrap_or_default().split(',').filter(|w| !w.is_empty()) {
+            match word {
+                "basename" => prefix.basename = true,
+                "color" => prefix.color = true,
+                "time" => prefix.time = true,
+                _ => return Err(format!("invalid prefix format: '{}'", word)),
+            }
+        }
+        Ok(prefix)
+    }
+
+    /// What goes in front of every line of `file`, `index` is the
+    /// order the file showed up in
+    pub fn render(&self, file: &str, index: usize) -> String {
+        let mut name = display_name(file);
+        if self.basename {
+            name = name.rsplit('/').find(|n| !n.is_empty()).unwrap_or(name);
+        }
+        let mut rendered = String::new();
+        if self.time {
+            rendered.push_str(&chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z ").to_string());
+        }
+        match self.color {
+            true => rendered.push_str(&format!("{}{}:{} ", COLORS[index % COLORS.len()], name, COLOR_RESET)),
+            false => rendered.push_str(&format!("{}: ", name)),
+        }
+        rendered
+    }
+}
+
+/// Checkpoints shared by every watcher, saved each time one of them reads
 #[derive(Debug)]
+struct ResumeState {
+    pub path: PathBuf,
+    pub registry: Mutex<Registry>,
+}
+
+impl ResumeState {
+    pub fn load(path: PathBuf) -> Result<ResumeState, io::Error> {
+        let registry = Mutex::new(Registry::load(&path)?);
+        Ok(ResumeState { path, registry })
+    }
+
+    pub fn record(&self, f: &util::file::FileDetail) {
+        let checkpoint = match f.checkpoint() {
+            Some(c) => c,
+            None => return,
+        };
+        let mut registry = self.registry.lock().unwrap();
+        if registry.get(&checkpoint.path) == Some(&checkpoint) {
+            return;
+        }
+        debug!("recording {:?}", checkpoint);
+        registry.set(checkpoint);
+        if let Err(e) = registry.save(&self.path) {
+            warn!("not able to save {} => {}", self.path.display(), e);
+        }
+    }
+}
+
+/// --match and --exclude, lines are kept when they match one of the
+/// first, if any are given, and none of the others
+#[derive(Debug)]
+struct Patterns {
+    matches: Vec<Regex>,
+    excludes: Vec<Regex>,
+    /// what --match matched is colored as grep does
+    highlight: bool,
+}
+
+impl Patterns {
+    fn new(matches: &[String], excludes: &[String], highlight: bool) -> Result<Patterns, String> {
+        let compile = |patterns: &[String]| patterns.iter()
+            .map(|p| Regex::new(p).map_err(|_| format!("invalid regular expression: '{}'", p)))
+            .collect::<Result<Vec<Regex>, String>>();
+        Ok(Patterns { matches: compile(matches)?, excludes: compile(excludes)?, highlight })
+    }
+
+    /// `line` is matched without its new line, for $ to match at its end
+    fn keeps(&self, line: &[u8]) -> bool {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        (self.matches.is_empty() || self.matches.iter().any(|r| r.is_match(line)))
+            && !self.excludes.iter().any(|r| r.is_match(line))
+    }
+
+    /// Adds `line` to `kept`, its matches highlighted when asked to
+    fn write(&self, line: &[u8], kept: &mut Vec<u8>) {
+        let text = line.strip_suffix(b"\n").unwrap_or(line);
+        let mut found: Vec<(usize, usize)> = match self.highlight {
+            true => self.matches.iter().flat_map(|r| r.find_iter(text)).map(|m| (m.start(), m.end())).filter(|(start, end)| start < end).collect(),
+            false => Vec::new(),
+        };
+        found.sort_unstable();
+        let mut written = 0;
+        for (start, end) in found {
+            // overlapping matches of different patterns are colored as one
+            if end <= written {
+                continue;
+            }
+            let start = start.max(written);
+            kept.extend_from_slice(&line[written..start]);
+            kept.extend_from_slice(HIGHLIGHT.as_bytes());
+            kept.extend_from_slice(&line[start..end]);
+            kept.extend_from_slice(HIGHLIGHT_END.as_bytes());
+            written = end;
+        }
+        kept.extend_from_slice(&line[written..]);
+    }
+}
+
+/// Keeps the lines asked for by --since, --until, --match and --exclude.
+/// Lines without a timestamp go along with the line before them as stack traces do
+#[derive(Debug, Clone)]
+struct LineFilter {
+    window: Option<Arc<Window>>,
+    patterns: Option<Arc<Patterns>>,
+    place: Place,
+    /// a line still being written, its timestamp or match may not be complete
+    partial: Vec<u8>,
+    partial_at: Cursor,
+}
+
+impl LineFilter {
+    fn new(window: Option<Arc<Window>>, patterns: Option<Arc<Patterns>>) -> LineFilter {
+        let place = match &window {
+            Some(w) if w.since.is_some() => Place::Before,
+            _ => Place::Within,
+        };
+        LineFilter { window, patterns, place, partial: Vec::new(), partial_at: Cursor::at(0) }
+    }
+
+    /// The complete lines of `content`, read `at`, kept in runs of lines
+    /// following each other, the last line waits for its end
+    fn apply(&mut self, content: Vec<u8>, at: Cursor) -> Vec<(Cursor, Vec<u8>)> {
+        if self.partial.is_empty() {
+            self.partial_at = at;
+        }
+        self.partial.extend_from_slice(&content);
+        let end = match memchr::memrchr(b'\n', &self.partial) {
+            Some(end) => end + 1,
+            None => return Vec::new(),
+        };
+        let rest = self.partial.split_off(end);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        let complete_at = self.partial_at;
+        self.partial_at = complete_at.after(&complete);
+        self.keep(&complete, complete_at)
+    }
+
+    /// The last line, once the file ends without its new line
+    fn finish(&mut self) -> Vec<(Cursor, Vec<u8>)> {
+        let partial = std::mem::take(&mut self.partial);
+        self.keep(&partial, self.partial_at)
+    }
+
+    fn keep(&mut self, lines: &[u8], mut at: Cursor) -> Vec<(Cursor, Vec<u8>)> {
+        let mut kept: Vec<(Cursor, Vec<u8>)> = Vec::new();
+        // where the last run kept ends in the file
+        let mut run_end = None;
+        for line in lines.split_inclusive(|b| *b == b'\n') {
+            let line_at = at;
+            at = at.after(line);
+            // the file is done with once past --until, even if late lines follow
+            if self.past_until() {
+                break;
+            }
+            if let Some(place) = self.window.as_ref().and_then(|w| w.place(line)) {
+                self.place = place;
+            }
+            if self.place != Place::Within || !self.patterns.as_ref().is_none_or(|p| p.keeps(line)) {
+                continue;
+            }
+            if run_end != Some(line_at.offset) {
+                kept.push((line_at, Vec::new()));
+            }
+            let run = &mut kept.last_mut().unwrap().1;
+            match &self.patterns {
+                Some(p) => p.write(line, run),
+                None => run.extend_from_slice(line),
+            }
+            run_end = Some(at.offset);
+        }
+        kept
+    }
+
+    fn past_until(&self) -> bool {
+        self.place == Place::After
+    }
+}
+
+/// --max-lines-per-sec, the lines of a file over the limit are left out
+/// and counted, a marker tells how many once lines are let through again
+#[derive(Debug, Clone)]
+struct Throttle {
+    max: u64,
+    second: time::Instant,
+    passed: u64,
+    suppressed: u64,
+    /// whether the last line was let through, while it isn't complete
+    /// the rest of it goes the same way
+    partial: Option<bool>,
+}
+
+impl Throttle {
+    fn new(max: u64) -> Throttle {
+        Throttle { max, second: time::Instant::now(), passed: 0, suppressed: 0, partial: None }
+    }
+
+    /// The lines of `content`, read `at`, let through in runs of lines
+    /// following each other
+    fn pass(&mut self, mut at: Cursor, content: Vec<u8>) -> Vec<(Cursor, Vec<u8>)> {
+        let mut runs: Vec<(Cursor, Vec<u8>)> = Vec::new();
+        let mut run_end = None;
+        for line in content.split_inclusive(|b| *b == b'\n') {
+            let line_at = at;
+            at = at.after(line);
+            let through = match self.partial {
+                Some(through) => through,
+                None => {
+                    if let Some(marker) = self.roll(line_at) {
+                        runs.push(marker);
+                        run_end = None;
+                    }
+                    self.admit()
+                },
+            };
+            self.partial = if line.ends_with(b"\n") { None } else { Some(through) };
+            if !through {
+                continue;
+            }
+            if run_end != Some(line_at.offset) {
+                runs.push((line_at, Vec::new()));
+            }
+            runs.last_mut().unwrap().1.extend_from_slice(line);
+            run_end = Some(at.offset);
+        }
+        runs
+    }
+
+    fn admit(&mut self) -> bool {
+        if self.passed < self.max {
+            self.passed += 1;
+            true
+        } else {
+            self.suppressed += 1;
+            false
+        }
+    }
+
+    /// Starts counting again once a second has passed, with the marker
+    /// of what was left out in the last one if anything was
+    fn roll(&mut self, at: Cursor) -> Option<(Cursor, Vec<u8>)> {
+        if self.second.elapsed() < time::Duration::from_secs(1) || self.partial.is_some() {
+            return None;
+        }
+        self.second = time::Instant::now();
+        self.passed = 0;
+        self.last(at)
+    }
+
+    /// The marker of what was left out, if anything was
+    fn last(&mut self, at: Cursor) -> Option<(Cursor, Vec<u8>)> {
+        if self.suppressed == 0 {
+            return None;
+        }
+        // a line let through half way is ended before the marker
+        let end = if self.partial == Some(true) { "\n" } else { "" };
+        let marker = format!("{}[... {} lines suppressed ...]\n", end, std::mem::take(&mut self.suppressed));
+        Some((at, marker.into_bytes()))
+    }
+}
+
+/// What is done with lines when the output channel is full
+#[derive(Debug, Clone)]
+enum Overflow {
+    /// wait for room, the default, every file waits for the slowest
+    Block,
+    /// make room dropping the line waiting the longest
+    DropOldest(crossbeam::channel::Receiver<Line>),
+    /// drop the line that doesn't fit
+    DropNewest,
+}
+
+#[derive(Debug, Clone)]
 struct TailOption {
     pub read_strategy: FileReadStrategy,
     pub follow: bool,
-    pub max_unchanged_stats: usize,
+    pub by_name: bool,
     pub pid: usize,
-    pub quiet: bool,
     pub retry: bool,
     pub sleep: u64,
-    pub verbose: bool,
+    /// look at every file each sleep interval rather than waiting on inotify
+    pub poll: bool,
+    pub decompress: bool,
     pub file: String,
     pub output_channel: crossbeam::channel::Sender<Line>,
+    pub resume_state: Option<Arc<ResumeState>>,
+    /// found by discovery, the watcher ends once the file is deleted
+    pub retire: bool,
+    pub filter: Option<LineFilter>,
+    /// where the next bytes sent start
+    pub cursor: Cursor,
+    pub inode: Option<u64>,
+    pub throttle: Option<Throttle>,
+    pub overflow: Overflow,
+    /// lines dropped by the overflow policy, shared by every file
+    pub dropped: Arc<AtomicU64>,
 }
 
 impl TailOption {
     pub fn wait(&self) {
         thread::sleep(time::Duration::from_millis(self.sleep));
     }
+
+    /// Sends `content` read at the cursor, nothing kept out of something
+    /// isn't sent at all as it isn't worth a header
+    pub fn send(&mut self, content: Vec<u8>) {
+        let at = self.cursor;
+        self.cursor = at.after(&content);
+        let kept = match self.filter.as_mut() {
+            Some(filter) if !content.is_empty() => filter.apply(content, at),
+            _ => vec![(at, content)],
+        };
+        self.pass(kept);
+    }
+
+    /// Sends what the filters kept, as much of it as --max-lines-per-sec lets through
+    fn pass(&mut self, kept: Vec<(Cursor, Vec<u8>)>) {
+        for (at, content) in kept {
+            let passed = match self.throttle.as_mut() {
+                Some(throttle) if !content.is_empty() => throttle.pass(at, content),
+                _ => vec![(at, content)],
+            };
+            for (at, content) in passed {
+                self.emit(at, content);
+            }
+        }
+    }
+
+    /// Tells how many lines --max-lines-per-sec left out once its second
+    /// is over, even if no other line comes
+    pub fn tick(&mut self) {
+        let at = self.cursor;
+        if let Some((at, marker)) = self.throttle.as_mut().and_then(|t| t.roll(at)) {
+            self.emit(at, marker);
+        }
+    }
+
+    /// Sends what the filter held back, the file is read for the last time
+    pub fn finish(&mut self) {
+        let rest = self.filter.as_mut().map(|f| f.finish()).unwrap_or_default();
+        self.pass(rest);
+        let at = self.cursor;
+        if let Some((at, marker)) = self.throttle.as_mut().and_then(|t| t.last(at)) {
+            self.emit(at, marker);
+        }
+    }
+
+    fn emit(&self, at: Cursor, content: Vec<u8>) {
+        let mut line = Line{ from_file: Some(self.file.clone()), content, offset: at.offset, line_number: at.line, inode: self.inode };
+        let oldest = match &self.overflow {
+            Overflow::Block => {
+                if let Err(e) = self.output_channel.send(line) {
+                    error!("output channel is closed => {}", e);
+                }
+                return;
+            },
+            Overflow::DropOldest(oldest) => Some(oldest),
+            Overflow::DropNewest => None,
+        };
+        loop {
+            let full = match self.output_channel.try_send(line) {
+                Ok(()) => return,
+                Err(TrySendError::Full(full)) => full,
+                Err(TrySendError::Disconnected(_)) => {
+                    error!("output channel is closed");
+                    return;
+                },
+            };
+            let rx = match oldest {
+                Some(rx) => rx,
+                None => return self.count_dropped(&full),
+            };
+            // the output may have taken it in the meantime
+            if let Ok(old) = rx.try_recv() {
+                self.count_dropped(&old);
+            }
+            line = full;
+        }
+    }
+
+    fn count_dropped(&self, dropped: &Line) {
+        let lines = memchr::memchr_iter(b'\n', &dropped.content).count().max(1) as u64;
+        if self.dropped.fetch_add(lines, Ordering::Relaxed) == 0 {
+            eprintln!("tail: output can't keep up, dropping lines");
+        }
+        debug!("dropped {} lines of {:?}", lines, dropped.from_file);
+    }
+
+    /// Where the previous run stopped reading this file, if it can be resumed
+    pub fn resume_offset(&self) -> Option<usize> {
+        let state = self.resume_state.as_ref()?;
+        let registry = state.registry.lock().unwrap();
+        let checkpoint = registry.get(&self.file)?;
+        util::file::new(self.file.clone()).resume_offset(checkpoint)
+    }
+
+    pub fn record(&self, f: &util::file::FileDetail) {
+        if let Some(state) = &self.resume_state {
+            state.record(f);
+        }
+    }
+
+    pub fn pid_alive(&self) -> bool {
+        self.pid == 0 || std::path::Path::new(&format!("/proc/{}", self.pid)).exists()
+    }
 }
 
 macro_rules! good_togo {
-    ($last_file_size:expr, $f:expr, $read_bunch:expr) => {{
+    ($stamp:expr, $fingerprint:expr, $behind:expr, $f:expr, $read_bunch:expr) => {{
         let mut ok = true;
+        let fingerprint: &[u8] = &$fingerprint;
         if !$f.is_stdin() {
-            let cur_file_size = match $f.len() {
-                Ok(s) => s,
+            let cur_stamp = match $f.stamp() {
+                Ok(s) => Some(s),
                 Err(e) => {
-                    warn!("good_togo error found on file size => {}", e);
+                    warn!("good_togo error found on file stamp => {}", e);
                     ok = false;
-                    0
+                    None
                 },
             };
-            debug!("last file size ({}) <=> current file size ({})", $last_file_size, cur_file_size);
-            if cur_file_size < $last_file_size {
-                debug!("File size is lower than previously, starting from zero");
-                $read_bunch = 0;
-            } else if cur_file_size == $last_file_size {
-                debug!("File size hasn't changed, ignoring though");
-                ok = false;                    
+            debug!("last stamp ({:?}) <=> current stamp ({:?})", $stamp, cur_stamp);
+            // the first look is always good, procfs files report no size at all
+            if let (Some(last), Some(cur)) = ($stamp, cur_stamp) {
+                if cur.size < last.size {
+                    debug!("File size is lower than previously, starting from zero");
+                    eprintln!("tail: {}: file truncated", $f.path());
+                    $read_bunch = 0;
+                } else if cur == last && !$behind {
+                    debug!("File hasn't changed, ignoring though");
+                    ok = false;
+                } else if !fingerprint.is_empty()
+                    && $f.bytes_before($read_bunch as u64, fingerprint.len()).map_or(false, |b| b != fingerprint) {
+                    // the last bytes read aren't there anymore, the file was rewritten in place
+                    debug!("File was rewritten, starting from zero");
+                    eprintln!("tail: {}: file truncated", $f.path());
+                    $read_bunch = 0;
+                }
+            }
+            if cur_stamp.is_some() {
+                $stamp = cur_stamp;
             }
-            $last_file_size = cur_file_size;
         }
         ok
     }};
 }
 
-fn file_watcher(to: &mut TailOption){
-    debug!("file_watcher => {:?}", to);
-    let mut notify_error = true;
-    let mut last_file_size: u64 = 0;
+/// One followed file, looked at again each time `poll` is called
+struct Watcher {
+    to: TailOption,
+    /// None until the file is first looked at, problems are reported
+    /// when it changes so each one is reported once
+    accessible: Option<bool>,
+    /// inode of the file being followed, None until it is first opened
+    inode: Option<u64>,
+    succeeded: bool,
+    stamp: Option<Stamp>,
+    /// the last bytes read, to tell a file rewritten in place from one written to
+    fingerprint: Vec<u8>,
+    /// the last poll ran out of budget before the end of the file
+    behind: bool,
+    done: bool,
+}
+
+impl Watcher {
+    fn new(mut to: TailOption) -> Watcher {
+        debug!("file_watcher => {:?}", to);
+        if to.follow && to.file == "-" {
+            match stdin::kind() {
+                stdin::Kind::Pipe => {
+                    info!("stdin is a pipe, ignoring -f");
+                    to.follow = false;
+                },
+                stdin::Kind::Terminal => eprintln!("tail: warning: following standard input indefinitely is ineffective"),
+                _ => {},
+            }
+        }
+
+        if let Some(offset) = to.resume_offset() {
+            info!("resuming {} from {}", to.file, offset);
+            to.read_strategy = FileReadStrategy::FromByte(offset);
+        }
+        Watcher { to, accessible: None, inode: None, succeeded: true, stamp: None, fingerprint: Vec::new(), behind: false, done: false }
+    }
 
-    if to.file == "-".to_string() {
-        let content = "tail: warning: following standard input indefinitely is ineffective\n".to_string();
-        to.output_channel.send(Line{ from_file: None, content: content });
+    /// The directory holding the file, as given
+    fn dir(&self) -> &std::path::Path {
+        std::path::Path::new(&self.to.file).parent().unwrap_or_else(|| std::path::Path::new(""))
     }
 
-    'outter: loop {
+    /// Reads whatever is new in the file, up to `budget` chunks when given,
+    /// done is set once there is nothing left to follow
+    fn poll(&mut self, budget: Option<usize>) {
+        let to = &mut self.to;
         info!("file_watcher looping => {}", to.file.clone());
         let mut f = util::file::new(to.file.clone());
-        let file_len = match f.len() {
-            Ok(v) => v,
-            _ => 0,
-        } as usize;
+        f.set_decompress(to.decompress);
+        // compressed files can't seek, they are streamed from the beginning
+        let seekable = f.is_seekable();
+        let file_len = f.len().unwrap_or_default() as usize;
         let start_pos = match to.read_strategy {
+            _ if !seekable => 0,
             FileReadStrategy::FromByte(v) => if file_len > v { v } else { file_len },
-            FileReadStrategy::LastBytes(v) => if file_len > v { file_len - v } else { 0 },
+            FileReadStrategy::LastBytes(v) => file_len.saturating_sub(v),
             _ => 0,
         };
         info!("file_watcher start_pos => {}", start_pos);
-        match f.prepare(start_pos){
-            Err(e) => {
-                if notify_error {
-                    notify_error = false;
-                    warn!("error found when trying to open file: {} - {}", to.file.clone(), e);
-                    let content = format!("tail cannot open '{}' for reading: {}\n", to.file.clone(), e);
-                    to.output_channel.send(Line{ from_file: None, content: content });
-                }
-                if to.retry{
-                    to.wait();
-                    continue
-                } else {
-                    break;
-                }
-            },
-            _ => { },
+        if let Err(e) = f.prepare(start_pos) {
+            if to.retire && e.kind() == io::ErrorKind::NotFound {
+                info!("{} is gone, retiring its watcher", to.file);
+                self.done = true;
+                return;
+            }
+            warn!("error found when trying to open file: {} - {}", to.file.clone(), e);
+            match self.accessible {
+                None => {
+                    self.succeeded = false;
+                    match e.kind() {
+                        io::ErrorKind::IsADirectory => eprintln!("tail: error reading '{}': {}", to.file, util::error::describe(&e)),
+                        _ => eprintln!("tail: cannot open '{}' for reading: {}", to.file, util::error::describe(&e)),
+                    }
+                    self.done = !(to.retry && to.pid_alive());
+                },
+                // by descriptor the file is still there for us, only its name is gone
+                Some(true) if to.by_name && to.retry => {
+                    eprintln!("tail: '{}' has become inaccessible: {}", to.file, util::error::describe(&e));
+                },
+                Some(true) if to.by_name => {
+                    self.succeeded = false;
+                    eprintln!("tail: {}: {}", to.file, util::error::describe(&e));
+                    self.done = true;
+                },
+                _ => {},
+            }
+            self.accessible = Some(false);
+            return;
         };
+        let inode = f.inode();
+        let appeared = self.accessible == Some(false) && (to.by_name || self.inode.is_none());
+        let replaced = self.accessible == Some(true) && self.inode.is_some() && inode != self.inode;
+        if replaced && !to.by_name {
+            trace!("{} was replaced, following the previous file", to.file);
+            return;
+        }
+        if appeared || replaced {
+            let how = if appeared { "has appeared" } else { "has been replaced" };
+            eprintln!("tail: '{}' {};  following new file", to.file, how);
+            to.read_strategy = FileReadStrategy::FromByte(0);
+            self.stamp = None;
+            self.fingerprint.clear();
+            if let Err(e) = f.prepare(0) {
+                warn!("{} is gone again => {}", to.file, e);
+                return;
+            }
+        }
+        self.accessible = Some(true);
+        self.inode = inode;
+        to.inode = inode;
+        if let FileReadStrategy::Since = to.read_strategy {
+            // log lines come in time order, the first one to print is looked up rather than read up to
+            let window = to.filter.as_ref().and_then(|f| f.window.clone());
+            let start = match &window {
+                Some(window) if seekable => f.bisect_lines(|line| window.place(line).map(|p| p != Place::Before)).unwrap_or(0),
+                _ => 0,
+            } as usize;
+            info!("{} starts at {} since {:?}", to.file, start, window.and_then(|w| w.since));
+            if start > 0 {
+                if let Err(e) = f.prepare(start) {
+                    warn!("not able to reopen {} => {}", to.file, e);
+                    return;
+                }
+            }
+            to.read_strategy = FileReadStrategy::FromByte(start);
+        }
         info!("file read strategy => {:?}", to.read_strategy);
-        let mut last_read = 0;
         match &to.read_strategy {
             FileReadStrategy::FromByte(v) => {
                 let mut read_bunch = *v;
-                if good_togo!(last_file_size, f, read_bunch) {
-                    last_read = read_bunch;
-                    loop {
-                        match f.read_chunk(1) {
-                            Some(b) => {
-                                last_read +=1;
-                                let content = String::from_utf8_lossy(&b).to_string();
-                                to.output_channel.send(Line{ from_file: Some(to.file.clone()), content: content });
-                            },
-                            Nome => {
-                                debug!("got none on read chunk");
-                                to.read_strategy = FileReadStrategy::FromByte(last_read);
-                                break;
-                            },
+                if good_togo!(self.stamp, self.fingerprint, self.behind, f, read_bunch) {
+                    if !seekable {
+                        f.walk_buffer_bytes(read_bunch);
+                        read_bunch = f.position() as usize;
+                    } else if read_bunch != *v {
+                        // truncated, prepare went to where the file used to end
+                        if let Err(e) = f.prepare(read_bunch) {
+                            warn!("not able to reopen {} => {}", to.file, e);
+                            return;
                         }
                     }
+                    // stdin goes on where it was, a file may be read elsewhere than the last time
+                    if !f.is_stdin() && to.cursor.offset != read_bunch as u64 {
+                        to.cursor = Cursor::at(read_bunch as u64);
+                    }
+                    let mut last_read = read_bunch;
+                    let mut chunks = 0;
+                    self.behind = false;
+                    while let Some(b) = f.read_chunk(CHUNK_SIZE) {
+                        last_read += b.len();
+                        to.send(b);
+                        chunks += 1;
+                        // streams are read from their start each time, they can't be left halfway
+                        if seekable && !f.is_stdin() && budget.is_some_and(|max| chunks >= max) {
+                            debug!("{} is read for this pass, going on at {}", to.file, last_read);
+                            self.behind = true;
+                            break;
+                        }
+                    }
+                    debug!("got none on read chunk");
+                    to.read_strategy = FileReadStrategy::FromByte(last_read);
+                    to.record(&f);
                 }
             },
             FileReadStrategy::LastBytes(v) => {
                 let mut read_bunch = *v;
-                if good_togo!(last_file_size, f, read_bunch) {
-                    let (size, buffer): (usize, Vec<u8>) = f.last_bytes(read_bunch);                    
-                    last_read = size;
-                    to.read_strategy = FileReadStrategy::FromByte(last_read);                
-                    let content = String::from_utf8_lossy(&buffer).to_string();
-                    to.output_channel.send(Line{ from_file: Some(to.file.clone()), content: content });
+                if good_togo!(self.stamp, self.fingerprint, self.behind, f, read_bunch) {
+                    let (_, buffer): (usize, Vec<u8>) = f.last_bytes(read_bunch);                    
+                    to.read_strategy = FileReadStrategy::FromByte(f.position() as usize);                
+                    to.cursor = Cursor { offset: f.position() - buffer.len() as u64, line: None };
+                    to.send(buffer);
+                    to.record(&f);
                 }                
             },
             FileReadStrategy::LastLines(v) => {
                 let mut read_bunch = *v;
-                if good_togo!(last_file_size, f, read_bunch) {
-                    let (size, buffer): (usize, Vec<String>) = f.last_lines(read_bunch);                    
-                    last_read = size;
-                    to.read_strategy = FileReadStrategy::FromLine(last_read);                
-                    let content = buffer.join("");
-                    to.output_channel.send(Line{ from_file: Some(to.file.clone()), content: content });
-                    if f.is_stdin() {
-                        debug!("FromLine found NONE when reading from stdin, aborting");
-                        break 'outter;
-                    }
+                if good_togo!(self.stamp, self.fingerprint, self.behind, f, read_bunch) {
+                    let (size, buffer): (usize, Vec<Vec<u8>>) = f.last_lines(read_bunch);                    
+                    // following goes on by bytes, a line still being written is counted otherwise
+                    to.read_strategy = FileReadStrategy::FromByte(f.position() as usize);                
+                    let skipped = (size - buffer.len()) as u64;
+                    let buffer = buffer.concat();
+                    to.cursor = Cursor { offset: f.position() - buffer.len() as u64, line: Some(skipped) };
+                    to.send(buffer);
+                    to.record(&f);
                 }                
             },
             FileReadStrategy::FromLine(v) => {
                 let mut read_bunch = *v;
-                if good_togo!(last_file_size, f, read_bunch) {
-                    f.walk_buffer_lines(read_bunch);
-                    last_read = read_bunch;
-                    loop {
-                        match f.read_line() {
-                            Some(b) => {
-                                info!("FromLine read => {}", b.clone());
-                                last_read +=1;
-                                to.output_channel.send(Line{ from_file: Some(to.file.clone()), content: b });
-                            },
-                            Nome => {
-                                debug!("got none on read line");
-                                if f.is_stdin() {
-                                    debug!("FromLine found NONE when reading from stdin, aborting");
-                                    break 'outter;
-                                }
-                                to.read_strategy = FileReadStrategy::FromLine(last_read);
-                                break;
-                            },
-                        }
+                if good_togo!(self.stamp, self.fingerprint, self.behind, f, read_bunch) {
+                    let walked = f.walk_buffer_lines(read_bunch);
+                    to.cursor = Cursor { offset: f.position(), line: Some(walked as u64) };
+                    while let Some(b) = f.read_line_bytes() {
+                        info!("FromLine read => {}", String::from_utf8_lossy(&b));
+                        to.send(b);
                     }
+                    debug!("got none on read line");
+                    to.record(&f);
+                    to.read_strategy = FileReadStrategy::FromByte(f.position() as usize);
                 }
                 
             },
             _ => {},
         }
 
+        if let Some(e) = f.take_read_error() {
+            eprintln!("tail: error reading '{}': {}", to.file, util::error::describe(&e));
+            self.succeeded = false;
+        }
+        to.tick();
+        if seekable {
+            self.fingerprint = f.bytes_before(f.position(), FINGERPRINT_SIZE).unwrap_or_default();
+        }
+        if f.is_stdin() {
+            // stdin goes on from where it was left, there is nothing to skip next time
+            to.read_strategy = FileReadStrategy::FromByte(0);
+        }
         if !to.follow {
-            debug!("not to follow - breaking the loop");
-            break;
-        };
-        debug!("file_watcher, going to sleep for {}ms", to.sleep);
-        to.wait();
+            debug!("not to follow - done with {}", to.file);
+            to.finish();
+            self.done = true;
+        } else if to.filter.as_ref().is_some_and(|f| f.past_until()) {
+            debug!("past --until - done with {}", to.file);
+            self.done = true;
+        } else if !to.pid_alive() {
+            debug!("process {} is gone - done with {}", to.pid, to.file);
+            self.done = true;
+        } else if f.is_stdin() && stdin::kind() != stdin::Kind::File {
+            // waiting on a fifo or a terminal would hold up every other file
+            if let Err(e) = stdin::set_nonblocking() {
+                warn!("not able to stop waiting on stdin, not following it => {}", e);
+                self.done = true;
+            }
+        }
     }
-    info!("FileWatcher main loop broke, ending up the thread");
 }
 
-fn output_collector(rx: crossbeam::channel::Receiver<Line>, close: crossbeam::channel::Receiver<bool>) {
-    let mut last_read = String::new();
+/// Follows every file from this one thread. Inotify on the directories of
+/// the files says which of them changed, every sleep interval all of them
+/// are looked at anyway: files not there yet, stdin and files inotify
+/// doesn't see changing, such as those on network filesystems. New files of the discovery are followed from
+/// their first byte, files that are gone are left.
+fn follow(mut watchers: Vec<Watcher>, discovery: Discovery, template: TailOption) -> bool {
+    let mut succeeded = true;
+    let interval = time::Duration::from_millis(template.sleep);
+    let mut inotify = match util::inotify::Inotify::new() {
+        Ok(i) if !template.poll => Some(i),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("inotify is not available, polling every {}ms => {}", template.sleep, e);
+            None
+        },
+    };
+    let mut last_pass = time::Instant::now();
     loop {
-        for v in rx.try_iter(){
-            trace!("loopping output_collector => {:?}", v);
-            match v.from_file {
-                Some(file) => {
-                    if last_read != file {
-                        println!("\n==>  {}  <==", file);
-                        last_read = file;
-                    }
-                },
-                _ => {},
+        watchers.retain(|w| {
+            if w.done {
+                info!("not following {} anymore", w.to.file);
+                succeeded &= w.succeeded;
+            }
+            !w.done
+        });
+        if watchers.is_empty() && discovery.is_empty() {
+            if !succeeded {
+                eprintln!("tail: no files remaining");
             }
-            print!("{}", v.content);
+            break;
         }
-        if close.try_iter().collect::<Vec<bool>>().len() > 0 {
-            debug!("output_collector => recieve close message on close channel, quitting");
+        if !template.pid_alive() {
+            debug!("process {} is gone - breaking the loop", template.pid);
             break;
         }
+
+        // files left halfway are read on right away, taking turns
+        let behind = watchers.iter().any(|w| w.behind);
+        let mut changed: Option<Vec<util::inotify::Event>> = None;
+        if let Some(i) = inotify.as_mut() {
+            let wait = if behind { time::Duration::ZERO } else { interval.saturating_sub(last_pass.elapsed()) };
+            match i.wait(wait) {
+                Ok(events) => changed = Some(events),
+                Err(e) => {
+                    warn!("inotify failed, polling from now on => {}", e);
+                    inotify = None;
+                },
+            }
+        } else if !behind {
+            template.wait();
+        }
+
+        let full_pass = last_pass.elapsed() >= interval || changed.is_none();
+        let changed = changed.unwrap_or_default();
+        if full_pass {
+            trace!("full pass over {} files", watchers.len());
+            last_pass = time::Instant::now();
+        }
+        for w in watchers.iter_mut() {
+            if full_pass || w.behind || changed.iter().any(|e| e.path.as_os_str() == w.to.file.as_str()) {
+                w.poll(Some(CHUNKS_PER_PASS));
+            }
+        }
+
+        if full_pass || changed.iter().any(|e| e.created) {
+            for file in discovery.scan() {
+                if watchers.iter().any(|w| w.to.file == file) {
+                    continue;
+                }
+                info!("discovered {}", file);
+                let mut w = Watcher::new(TailOption{ file, ..template.clone() });
+                w.poll(Some(CHUNKS_PER_PASS));
+                watchers.push(w);
+            }
+        }
+
+        if let Some(i) = inotify.as_mut() {
+            let dirs = watchers.iter().filter(|w| w.to.file != "-").map(|w| w.dir()).chain(discovery.dirs().iter().map(|d| d.as_path()));
+            for dir in dirs {
+                if let Err(e) = i.watch_dir(dir) {
+                    trace!("not able to watch {} => {}", dir.display(), e);
+                }
+            }
+        }
+    }
+    for w in watchers {
+        succeeded &= w.succeeded;
+    }
+    succeeded
+}
+
+fn display_name(file: &str) -> &str {
+    match file {
+        "-" => "standard input",
+        _ => file,
     }
 }
 
+/// Block buffered output is still flushed once nothing else is waiting,
+/// followed lines would sit in the buffer until a block fills up otherwise
+fn flush_when_idle(rx: &crossbeam::channel::Receiver<Line>, out: &mut Output) {
+    if rx.is_empty() {
+        if let Err(e) = out.flush() {
+            error!("write error => {}", e);
+        }
+    }
+}
+
+fn output_collector(rx: crossbeam::channel::Receiver<Line>, quiet: bool, mut out: Output) {
+    let mut last_read: Option<String> = None;
+    for v in rx.iter() {
+        trace!("loopping output_collector => {:?}", v);
+        if let Some(file) = v.from_file {
+            if last_read.as_ref() != Some(&file) {
+                if !quiet {
+                    let separator = if last_read.is_some() { "\n" } else { "" };
+                    if let Err(e) = writeln!(out, "{}==> {} <==", separator, display_name(&file)) {
+                        error!("write error => {}", e);
+                    }
+                }
+                last_read = Some(file);
+            }
+        }
+        if let Err(e) = out.write_all(&v.content) {
+            error!("write error => {}", e);
+        }
+        flush_when_idle(&rx, &mut out);
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    debug!("output_collector => every sender is gone, quitting");
+}
+
+fn write_prefixed(out: &mut Output, prefix: &Prefix, file: &str, index: usize, lines: &[u8]) {
+    let rendered = prefix.render(file, index);
+    for line in lines.split_inclusive(|b| *b == b'\n') {
+        if let Err(e) = out.write_all(rendered.as_bytes()).and_then(|_| out.write_all(line)) {
+            error!("write error => {}", e);
+        }
+    }
+}
+
+/// Prints every line behind the prefix of its file, lines are only
+/// printed once complete so lines of different files are never spliced
+fn prefix_collector(rx: crossbeam::channel::Receiver<Line>, prefix: Prefix, mut out: Output) {
+    let mut pending: BTreeMap<String, (usize, Vec<u8>)> = BTreeMap::new();
+    for v in rx.iter() {
+        trace!("loopping prefix_collector => {:?}", v);
+        let file = v.from_file.unwrap_or_default();
+        let index = pending.len();
+        let (index, buffer) = pending.entry(file.clone()).or_insert((index, Vec::new()));
+        buffer.extend_from_slice(&v.content);
+        if let Some(end) = memchr::memrchr(b'\n', buffer) {
+            let complete: Vec<u8> = buffer.drain(..=end).collect();
+            write_prefixed(&mut out, &prefix, &file, *index, &complete);
+        }
+        flush_when_idle(&rx, &mut out);
+    }
+    // files ending without a new line get one, the next line would be spliced otherwise
+    for (file, (index, mut buffer)) in pending {
+        if !buffer.is_empty() {
+            buffer.push(b'\n');
+            write_prefixed(&mut out, &prefix, &file, index, &buffer);
+        }
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    debug!("prefix_collector => every sender is gone, quitting");
+}
+
+/// One line of --output=json
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    file: &'a str,
+    offset: u64,
+    /// counted from 1, null when reading didn't start at the top of the file
+    line_number: Option<u64>,
+    inode: Option<u64>,
+    received_at: String,
+    line: std::borrow::Cow<'a, str>,
+}
+
+fn write_json(out: &mut Output, file: &str, at: Cursor, inode: Option<u64>, lines: &[u8]) {
+    let received_at = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+    let mut at = at;
+    for line in lines.split_inclusive(|b| *b == b'\n') {
+        let json = JsonLine {
+            file,
+            offset: at.offset,
+            line_number: at.line.map(|n| n + 1),
+            inode,
+            received_at: received_at.clone(),
+            line: String::from_utf8_lossy(line.strip_suffix(b"\n").unwrap_or(line)),
+        };
+        at = at.after(line);
+        let written = serde_json::to_writer(&mut *out, &json).map_err(io::Error::from).and_then(|_| out.write_all(b"\n"));
+        if let Err(e) = written {
+            error!("write error => {}", e);
+        }
+    }
+}
+
+/// Writes every line as a json object, lines are only written once
+/// complete, a line cut short by the file being truncated or replaced
+/// is written as it is
+fn json_collector(rx: crossbeam::channel::Receiver<Line>, mut out: Output) {
+    let mut pending: BTreeMap<String, (Cursor, Option<u64>, Vec<u8>)> = BTreeMap::new();
+    for v in rx.iter() {
+        trace!("loopping json_collector => {:?}", v);
+        let file = v.from_file.unwrap_or_default();
+        let at = Cursor { offset: v.offset, line: v.line_number };
+        let (start, inode, buffer) = pending.entry(file.clone()).or_insert((at, v.inode, Vec::new()));
+        if !buffer.is_empty() && (start.after(buffer).offset != at.offset || *inode != v.inode) {
+            write_json(&mut out, &file, *start, *inode, buffer);
+            buffer.clear();
+        }
+        if buffer.is_empty() {
+            *start = at;
+            *inode = v.inode;
+        }
+        buffer.extend_from_slice(&v.content);
+        if let Some(end) = memchr::memrchr(b'\n', buffer) {
+            let complete: Vec<u8> = buffer.drain(..=end).collect();
+            write_json(&mut out, &file, *start, *inode, &complete);
+            *start = start.after(&complete);
+        }
+        flush_when_idle(&rx, &mut out);
+    }
+    for (file, (start, inode, buffer)) in pending {
+        if !buffer.is_empty() {
+            write_json(&mut out, &file, start, inode, &buffer);
+        }
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    debug!("json_collector => every sender is gone, quitting");
+}
+
+/// Writes the part of `file` picked by `strategy` last line first, behind
+/// its header. False when the file couldn't be read.
+fn reverse_file(file: &str, opt: &Opt, strategy: &FileReadStrategy, first: &mut bool, out: &mut dyn Write) -> bool {
+    let mut f = util::file::new(file.to_string());
+    f.set_decompress(opt.decompress);
+    if let Err(e) = f.prepare(0) {
+        info!("error found while preparing file {} => {}", file, e);
+        match e.kind() {
+            io::ErrorKind::IsADirectory => eprintln!("tail: error reading '{}': {}", file, util::error::describe(&e)),
+            _ => eprintln!("tail: cannot open '{}' for reading: {}", file, util::error::describe(&e)),
+        }
+        return false;
+    }
+    if !opt.quiet {
+        let separator = if *first { "" } else { "\n" };
+        if let Err(e) = writeln!(out, "{}==> {} <==", separator, display_name(file)) {
+            error!("write error => {}", e);
+        }
+    }
+    *first = false;
+    let reverse = Reverse { separator: Separator::text("\n"), before: false };
+    let written = f.spill().and_then(|_| {
+        let len = f.opened_len()?;
+        let (start, lines) = match *strategy {
+            FileReadStrategy::FromByte(b) => ((b as u64).min(len), None),
+            FileReadStrategy::LastBytes(b) => (len.saturating_sub(b as u64), None),
+            FileReadStrategy::FromLine(l) => {
+                f.walk_buffer_lines(l);
+                (f.position(), None)
+            },
+            FileReadStrategy::LastLines(l) => (0, Some(l as u64)),
+            _ => (0, None),
+        };
+        reverse.write(&f, start, len, lines, out)
+    });
+    if let Err(e) = written {
+        eprintln!("tail: error reading '{}': {}", file, util::error::describe(&e));
+        return false;
+    }
+    true
+}
+
+/// tail -r, the files are read through before anything is written
+fn reverse_files(opt: &Opt) -> bool {
+    if let Some(option) = opt.reverse_conflict() {
+        eprintln!("tail: -r can't be used with {}", option);
+        process::exit(1);
+    }
+    let strategy = match (&opt.bytes, &opt.lines) {
+        (None, None) => FileReadStrategy::FromByte(0),
+        _ => FileReadStrategy::pick(opt.bytes.clone(), opt.lines.clone()),
+    };
+    if let FileReadStrategy::None(e) = strategy {
+        eprintln!("tail: {}", e);
+        process::exit(1);
+    }
+    if matches!(strategy, FileReadStrategy::LastLines(0) | FileReadStrategy::LastBytes(0)) {
+        return true;
+    }
+    let mut out = util::output::stdout(Buffering::pick(opt.line_buffered, opt.unbuffered));
+    let mut first = true;
+    let mut succeeded = true;
+    for file in opt.files.iter() {
+        succeeded &= reverse_file(file, opt, &strategy, &mut first, &mut out);
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    succeeded
+}
+
 fn sleep_time(sleep: f64) -> u64 {
     (sleep * 1000.0) as u64
 }
 
 fn main() {
-    let mut line_number = 0;
-    let mut opt = Opt::from_args();
+    let mut opt = Opt::from_iter(util::obsolete::tail(std::env::args_os().collect()));
     opt.initialize();
     env_logger::from_env(env_logger::Env::default().default_filter_or("none")).init();
     //env_logger::init();
-    
+    if opt.reverse {
+        process::exit(if reverse_files(&opt) { 0 } else { 1 });
+    }
+
     let (s, r) = crossbeam::bounded(100);
-    let (close_tx, close_rx) = crossbeam::bounded(1);
+    let overflow = match opt.overflow.as_str() {
+        "drop-oldest" => Overflow::DropOldest(r.clone()),
+        "drop-newest" => Overflow::DropNewest,
+        _ => Overflow::Block,
+    };
+    let dropped = Arc::new(AtomicU64::new(0));
 
-    let output_thread = thread::spawn(move || { output_collector(r, close_rx.clone()) });
-    let mut file_watcher_pool = Vec::new();
-        
-    for file in opt.files {
-        let sc = s.clone();
-        let mut to = TailOption{
-            read_strategy: FileReadStrategy::pick(opt.bytes.clone(), opt.lines.clone()),
-            follow: opt.follow,
-            max_unchanged_stats: opt.max_unchanged_stats,
-            pid: opt.pid,
-            quiet: opt.quiet,
-            retry: opt.retry,
-            sleep: sleep_time(opt.sleep),
-            verbose: opt.verbose,
-            file: file.clone(),
-            output_channel: sc,
-        };
-        
-        match to.read_strategy {
-            FileReadStrategy::None(e) => {
-                println!("tail: {}", e);
-                continue;
+    let quiet = opt.quiet;
+    let out = util::output::stdout(Buffering::pick(opt.line_buffered, opt.unbuffered));
+    let by_name = opt.by_name();
+    if by_name && opt.files.iter().any(|f| f == "-") {
+        eprintln!("tail: cannot follow '-' by name");
+        process::exit(1);
+    }
+    let json = opt.output == "json";
+    let output_thread = match opt.prefix {
+        _ if json => thread::spawn(move || { json_collector(r, out) }),
+        Some(format) => match Prefix::parse(format.as_deref()) {
+            Ok(prefix) => thread::spawn(move || { prefix_collector(r, prefix, out) }),
+            Err(e) => {
+                eprintln!("tail: {}", e);
+                process::exit(1);
             },
-            _ => {},
-        }
-
-        debug!("{:?}", to);
+        },
+        None => thread::spawn(move || { output_collector(r, quiet, out) }),
+    };
+    let succeeded;
+    let resume_state = match opt.resume_state {
+        Some(path) => match ResumeState::load(path.clone()) {
+            Ok(state) => Some(Arc::new(state)),
+            Err(e) => {
+                eprintln!("tail: cannot read '{}': {}", path.display(), util::error::describe(&e));
+                process::exit(1);
+            },
+        },
+        None => None,
+    };
         
-        if opt.follow{
-            file_watcher_pool.push(
-                thread::spawn(move || {
-                    file_watcher(&mut to);
-                })
-            );
-        } else {
-            file_watcher(&mut to);
+    let mut read_strategy = FileReadStrategy::pick(opt.bytes.clone(), opt.lines.clone());
+    if let FileReadStrategy::None(e) = read_strategy {
+        eprintln!("tail: {}", e);
+        process::exit(1);
+    }
+    // nothing would ever be printed, gnu doesn't even open the files
+    if !opt.follow && matches!(read_strategy, FileReadStrategy::LastLines(0) | FileReadStrategy::LastBytes(0)) {
+        process::exit(0);
+    }
+    let window = match Window::new(opt.since.as_deref(), opt.until.as_deref(), chrono::Local::now()) {
+        Ok(window) if window.since.is_some() || window.until.is_some() => Some(Arc::new(window)),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("tail: {}", e);
+            process::exit(1);
+        },
+    };
+    let highlight = match opt.color.as_str() {
+        "always" => true,
+        "never" => false,
+        // escape sequences are no use to json readers
+        _ => !json && util::output::is_terminal(),
+    };
+    let patterns = match Patterns::new(&opt.matches, &opt.excludes, highlight) {
+        Ok(p) if !p.matches.is_empty() || !p.excludes.is_empty() => Some(Arc::new(p)),
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("tail: {}", e);
+            process::exit(1);
+        },
+    };
+    // lines are dropped whole, the filter holds back a line until it is complete
+    let filter = match (&window, &patterns, &overflow) {
+        (None, None, Overflow::Block) => None,
+        _ => Some(LineFilter::new(window, patterns)),
+    };
+    // --since picks where output starts unless -n or -c does
+    if opt.since.is_some() && opt.bytes.is_none() && opt.lines.is_none() {
+        read_strategy = FileReadStrategy::Since;
+    }
+
+    let (globs, files): (Vec<String>, Vec<String>) = opt.files.into_iter().partition(|f| is_glob(f));
+    for dir in opt.watch_dir.iter() {
+        if let Err(e) = std::fs::read_dir(dir) {
+            eprintln!("tail: cannot watch '{}': {}", dir.display(), util::error::describe(&e));
+            process::exit(1);
         }
     }
+    let discovery = match Discovery::new(opt.watch_dir, &opt.pattern, globs) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("tail: invalid pattern: {}", e);
+            process::exit(1);
+        },
+    };
 
-    for t in file_watcher_pool {
-        t.join();
+    let template = TailOption{
+        read_strategy,
+        follow: opt.follow,
+        by_name,
+        pid: opt.pid,
+        retry: opt.retry,
+        sleep: sleep_time(opt.sleep),
+        poll: opt.disable_inotify,
+        decompress: opt.decompress,
+        file: String::new(),
+        output_channel: s,
+        resume_state,
+        retire: false,
+        filter,
+        cursor: Cursor::at(0),
+        inode: None,
+        throttle: opt.max_lines_per_sec.map(Throttle::new),
+        overflow,
+        dropped: dropped.clone(),
+    };
+    let mut watchers = Vec::new();
+    let watched = files.into_iter().map(|f| (f, false)).chain(discovery.scan().into_iter().map(|f| (f, true)));
+    for (file, retire) in watched {
+        let mut w = Watcher::new(TailOption{ file, retire, ..template.clone() });
+        w.poll(None);
+        watchers.push(w);
+    }
+    if opt.follow {
+        // files showing up later are printed whole
+        let template = TailOption{ read_strategy: FileReadStrategy::FromByte(0), retire: true, ..template };
+        succeeded = follow(watchers, discovery, template);
+    } else {
+        succeeded = watchers.iter().all(|w| w.succeeded);
+        // their senders would keep the output collector waiting
+        drop(watchers);
+        drop(template);
     }
 
-    close_tx.send(true);
-    output_thread.join();
+    if output_thread.join().is_err() {
+        error!("output collector panicked");
+    }
+    let dropped = dropped.load(Ordering::Relaxed);
+    if dropped > 0 {
+        eprintln!("tail: {} lines dropped as the output couldn't keep up", dropped);
+    }
+    process::exit(if succeeded { 0 } else { 1 });
 }
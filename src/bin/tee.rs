@@ -0,0 +1,171 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+use rust_core_utils::util;
+
+use std::fs::OpenOptions;
+use std::io::{self, Read, Write};
+use std::process;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crossbeam::channel::{self, Receiver, Sender};
+use structopt::StructOpt;
+
+/// Bytes read from stdin at once
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Chunks an output can fall behind by before reading waits for it
+const BACKLOG: usize = 64;
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "tee", about = "Copy standard input to each FILE, and also to standard output.")]
+struct Opt {
+    /// append to the given FILEs, do not overwrite
+    #[structopt(short = "a", long = "append")]
+    append: bool,
+
+    /// ignore interrupt signals
+    #[structopt(short = "i", long = "ignore-interrupts")]
+    ignore_interrupts: bool,
+
+    /// diagnose errors writing to non pipes
+    #[structopt(short = "p")]
+    nopipe: bool,
+
+    /// set behavior on write error: warn to diagnose errors writing
+    ///  to any output, warn-nopipe to diagnose errors writing to any
+    ///  output not a pipe, exit to exit on error writing to any output,
+    ///  exit-nopipe to exit on error writing to any output not a pipe;
+    ///  the default MODE for the -p option is 'warn-nopipe'; without
+    ///  either, tee exits on error writing to a pipe and diagnoses
+    ///  errors writing to non pipe outputs
+    #[structopt(long = "output-error", name = "MODE", raw(require_equals = "true", possible_values = r#"&["warn", "warn-nopipe", "exit", "exit-nopipe"]"#))]
+    output_error: Option<Option<String>>,
+
+    #[structopt(name = "FILES")]
+    files: Vec<String>,
+
+}
+
+/// What a failed write does to tee
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputError {
+    /// broken pipes kill tee, other errors are diagnosed
+    Sigpipe,
+    Warn,
+    WarnNopipe,
+    Exit,
+    ExitNopipe,
+}
+
+impl OutputError {
+    fn pick(opt: &Opt) -> OutputError {
+        match opt.output_error.as_ref().map(|mode| mode.as_deref()) {
+            Some(Some("warn")) => OutputError::Warn,
+            Some(Some("exit")) => OutputError::Exit,
+            Some(Some("exit-nopipe")) => OutputError::ExitNopipe,
+            Some(_) => OutputError::WarnNopipe,
+            None if opt.nopipe => OutputError::WarnNopipe,
+            None => OutputError::Sigpipe,
+        }
+    }
+
+    /// Whether the error is diagnosed and makes tee fail, outputs
+    /// turning out to be closed pipes are left quietly in nopipe modes
+    fn fails(self, e: &io::Error) -> bool {
+        e.kind() != io::ErrorKind::BrokenPipe || !matches!(self, OutputError::WarnNopipe | OutputError::ExitNopipe)
+    }
+
+    fn exits(self) -> bool {
+        matches!(self, OutputError::Exit | OutputError::ExitNopipe)
+    }
+}
+
+/// Names are quoted as gnu does when they hold more than plain characters
+fn quote(name: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "._/+,:@%^-".contains(c);
+    match name.chars().all(plain) {
+        true => name.to_string(),
+        false => format!("'{}'", name),
+    }
+}
+
+/// Writes what comes through `chunks` to `out` until it's over or writing
+/// fails, false when the failure counts. Each output has its own thread
+/// so a slow one only holds reading back once its backlog is full.
+fn write_output(name: String, mut out: Box<dyn Write + Send>, chunks: Receiver<Arc<Vec<u8>>>, mode: OutputError) -> bool {
+    for chunk in chunks.iter() {
+        if let Err(e) = out.write_all(&chunk).and_then(|_| out.flush()) {
+            info!("error writing to {} => {}", name, e);
+            if !mode.fails(&e) {
+                return true;
+            }
+            eprintln!("tee: {}: {}", quote(&name), util::error::describe(&e));
+            if mode.exits() {
+                process::exit(1);
+            }
+            return false;
+        }
+    }
+    true
+}
+
+fn spawn_output(name: String, out: Box<dyn Write + Send>, mode: OutputError) -> (Sender<Arc<Vec<u8>>>, JoinHandle<bool>) {
+    let (s, r) = channel::bounded(BACKLOG);
+    (s, thread::spawn(move || write_output(name, out, r, mode)))
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    env_logger::init();
+    let mode = OutputError::pick(&opt);
+    // safety: signal dispositions are set before any thread is started
+    unsafe {
+        // rust ignores SIGPIPE, gnu tee dies of it unless told otherwise
+        if mode == OutputError::Sigpipe {
+            libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+        }
+        if opt.ignore_interrupts {
+            libc::signal(libc::SIGINT, libc::SIG_IGN);
+        }
+    }
+    let mut succeeded = true;
+    let mut outputs = vec![spawn_output("standard output".to_string(), Box::new(io::stdout()), mode)];
+    for file in opt.files.iter() {
+        let opened = OpenOptions::new().write(true).create(true).append(opt.append).truncate(!opt.append).open(file);
+        match opened {
+            Ok(f) => outputs.push(spawn_output(file.clone(), Box::new(f), mode)),
+            Err(e) => {
+                eprintln!("tee: {}: {}", quote(file), util::error::describe(&e));
+                succeeded = false;
+            },
+        }
+    }
+
+    let (senders, writers): (Vec<_>, Vec<_>) = outputs.into_iter().unzip();
+    let mut senders: Vec<Sender<Arc<Vec<u8>>>> = senders;
+    let mut stdin = io::stdin().lock();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    // reading stops once every output is gone
+    while !senders.is_empty() {
+        let n = match stdin.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                eprintln!("tee: read error: {}", util::error::describe(&e));
+                succeeded = false;
+                break;
+            },
+        };
+        let chunk = Arc::new(buffer[..n].to_vec());
+        senders.retain(|s| s.send(chunk.clone()).is_ok());
+    }
+    drop(senders);
+    for writer in writers {
+        succeeded &= writer.join().unwrap_or(false);
+    }
+    process::exit(if succeeded { 0 } else { 1 });
+}
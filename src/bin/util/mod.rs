@@ -1,3 +0,0 @@
-
-pub mod file;
-pub mod file_read_strategy;
@@ -0,0 +1,447 @@
+#[macro_use]
+extern crate log;
+extern crate env_logger;
+
+use rust_core_utils::util;
+
+use std::env;
+use std::ffi::CStr;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::ops::AddAssign;
+use std::process;
+
+use structopt::StructOpt;
+
+extern "C" {
+    fn iswprint(c: libc::wchar_t) -> libc::c_int;
+    fn iswspace(c: libc::wchar_t) -> libc::c_int;
+    fn wcwidth(c: libc::wchar_t) -> libc::c_int;
+}
+
+/// Spaces gnu takes as word separators too, unless POSIXLY_CORRECT is set
+const NO_BREAK_SPACES: &[char] = &['\u{a0}', '\u{2007}', '\u{202f}', '\u{2060}'];
+
+#[derive(StructOpt, Debug)]
+#[structopt(name = "wc", about = "Print newline, word, and byte counts for each FILE, and a total line if
+more than one FILE is specified.  A word is a non-zero-length sequence of
+printable characters delimited by white space.
+")]
+struct Opt {
+    /// print the byte counts
+    #[structopt(short = "c", long = "bytes")]
+    bytes: bool,
+
+    /// print the character counts
+    #[structopt(short = "m", long = "chars")]
+    chars: bool,
+
+    /// print the newline counts
+    #[structopt(short = "l", long = "lines")]
+    lines: bool,
+
+    /// read input from the files specified by
+    ///  NUL-terminated names in file F;
+    ///  If F is - then read names from standard input
+    #[structopt(long = "files0-from", name = "F")]
+    files0_from: Option<String>,
+
+    /// print the maximum display width
+    #[structopt(short = "L", long = "max-line-length")]
+    max_line_length: bool,
+
+    /// print the word counts
+    #[structopt(short = "w", long = "words")]
+    words: bool,
+
+    #[structopt(name = "FILES")]
+    files: Vec<String>,
+
+}
+
+impl Opt {
+    /// Lines, words and bytes are counted unless told otherwise
+    fn initialize(&mut self) {
+        if !(self.bytes || self.chars || self.lines || self.max_line_length || self.words) {
+            self.lines = true;
+            self.words = true;
+            self.bytes = true;
+        }
+    }
+
+    fn printed(&self) -> usize {
+        [self.lines, self.words, self.chars, self.bytes, self.max_line_length].iter().filter(|p| **p).count()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Counts {
+    lines: u64,
+    words: u64,
+    chars: u64,
+    bytes: u64,
+    max_line_length: u64,
+}
+
+impl AddAssign for Counts {
+    fn add_assign(&mut self, other: Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.max_line_length = self.max_line_length.max(other.max_line_length);
+    }
+}
+
+/// How much of the text has to be looked at
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scan {
+    /// new lines only, searched for rather than walked through
+    Lines,
+    /// every byte, single byte locales
+    Bytes,
+    /// every character, utf-8 locales
+    Chars,
+}
+
+/// Counts what is written into it, characters split between two writes are
+/// held until the next one
+struct Counter {
+    scan: Scan,
+    counts: Counts,
+    in_word: bool,
+    /// display width of the line so far
+    line_position: u64,
+    pending: Vec<u8>,
+    no_break_spaces: bool,
+}
+
+impl Counter {
+    fn new(scan: Scan) -> Counter {
+        Counter {
+            scan,
+            counts: Counts::default(),
+            in_word: false,
+            line_position: 0,
+            pending: Vec::new(),
+            no_break_spaces: env::var_os("POSIXLY_CORRECT").is_none(),
+        }
+    }
+
+    fn finish(mut self) -> Counts {
+        // bytes left of a character never completed aren't characters
+        self.end_line();
+        self.end_word();
+        if self.scan != Scan::Chars {
+            self.counts.chars = self.counts.bytes;
+        }
+        self.counts
+    }
+
+    fn end_line(&mut self) {
+        self.counts.max_line_length = self.counts.max_line_length.max(self.line_position);
+        self.line_position = 0;
+    }
+
+    fn end_word(&mut self) {
+        if self.in_word {
+            self.counts.words += 1;
+            self.in_word = false;
+        }
+    }
+
+    /// Walks through ascii as gnu does in the C locale, bytes beyond it aren't printable
+    fn ascii(&mut self, b: u8) {
+        match b {
+            b'\n' => {
+                self.counts.lines += 1;
+                self.end_line();
+                self.end_word();
+            },
+            b'\r' | b'\x0c' => {
+                self.end_line();
+                self.end_word();
+            },
+            b'\t' => {
+                self.line_position += 8 - self.line_position % 8;
+                self.end_word();
+            },
+            b' ' => {
+                self.line_position += 1;
+                self.end_word();
+            },
+            b'\x0b' => self.end_word(),
+            0x21..=0x7e => {
+                self.line_position += 1;
+                self.in_word = true;
+            },
+            _ => {},
+        }
+    }
+
+    fn char(&mut self, c: char) {
+        self.counts.chars += 1;
+        if c.is_ascii() {
+            return self.ascii(c as u8);
+        }
+        let wide = c as libc::wchar_t;
+        // safety: plain lookups in the tables of the locale set up in main
+        if unsafe { iswprint(wide) } == 0 {
+            return;
+        }
+        let width = unsafe { wcwidth(wide) };
+        if width > 0 {
+            self.line_position += width as u64;
+        }
+        if unsafe { iswspace(wide) } != 0 || (self.no_break_spaces && NO_BREAK_SPACES.contains(&c)) {
+            self.end_word();
+        } else {
+            self.in_word = true;
+        }
+    }
+
+    /// Invalid bytes are skipped one at a time as mbrtowc has them, they aren't characters
+    fn text(&mut self, mut data: &[u8]) {
+        loop {
+            let (valid, rest) = match std::str::from_utf8(data) {
+                Ok(text) => (text, &data[data.len()..]),
+                Err(e) => {
+                    let (valid, rest) = data.split_at(e.valid_up_to());
+                    // safety: from_utf8 checked the bytes up to valid_up_to
+                    let valid = unsafe { std::str::from_utf8_unchecked(valid) };
+                    match e.error_len() {
+                        Some(_) => (valid, &rest[1..]),
+                        None => {
+                            self.pending = rest.to_vec();
+                            (valid, &rest[rest.len()..])
+                        },
+                    }
+                },
+            };
+            for c in valid.chars() {
+                self.char(c);
+            }
+            if rest.is_empty() {
+                return;
+            }
+            data = rest;
+        }
+    }
+}
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.counts.bytes += buf.len() as u64;
+        match self.scan {
+            Scan::Lines => self.counts.lines += memchr::memchr_iter(b'\n', buf).count() as u64,
+            Scan::Bytes => buf.iter().for_each(|b| self.ascii(*b)),
+            Scan::Chars if self.pending.is_empty() => self.text(buf),
+            Scan::Chars => {
+                let joined = [std::mem::take(&mut self.pending).as_slice(), buf].concat();
+                self.text(&joined);
+            },
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Whether the locale picked by the environment encodes characters in utf-8
+fn utf8_locale() -> bool {
+    // safety: called before any other thread is around, the codeset is copied right away
+    unsafe {
+        libc::setlocale(libc::LC_ALL, b"\0".as_ptr() as *const libc::c_char);
+        let codeset = libc::nl_langinfo(libc::CODESET);
+        !codeset.is_null() && CStr::from_ptr(codeset).to_bytes() == b"UTF-8"
+    }
+}
+
+/// Width of the columns as gnu has it: as many digits as the size of the
+/// regular files put together, at least 7 when any isn't a regular file,
+/// 1 when a single count of a single file is printed
+fn number_width(files: &[String], printed: usize) -> usize {
+    if files.len() == 1 && printed == 1 {
+        return 1;
+    }
+    let mut minimum = 1;
+    let mut regular_total: u64 = 0;
+    for file in files {
+        let md = match file.as_str() {
+            "-" => fs::metadata("/dev/stdin"),
+            _ => fs::metadata(file),
+        };
+        match md {
+            Ok(md) if md.is_file() => regular_total += md.len(),
+            Ok(_) => minimum = 7,
+            Err(_) => {},
+        }
+    }
+    regular_total.to_string().len().max(minimum)
+}
+
+/// The file names --files0-from gives, the zero-length ones included
+fn read_files0(from: &str) -> Result<Vec<String>, io::Error> {
+    let mut names = Vec::new();
+    match from {
+        "-" => io::stdin().read_to_end(&mut names)?,
+        _ => File::open(from)?.read_to_end(&mut names)?,
+    };
+    if names.ends_with(b"\0") {
+        names.pop();
+    }
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(names.split(|b| *b == 0).map(|n| String::from_utf8_lossy(n).into_owned()).collect())
+}
+
+fn print_counts(counts: &Counts, name: Option<&str>, opt: &Opt, width: usize, out: &mut dyn Write) {
+    let shown = [
+        (opt.lines, counts.lines),
+        (opt.words, counts.words),
+        (opt.chars, counts.chars),
+        (opt.bytes, counts.bytes),
+        (opt.max_line_length, counts.max_line_length),
+    ];
+    let mut line: Vec<String> = shown.iter().filter(|(p, _)| *p).map(|(_, c)| format!("{:>1$}", c, width)).collect();
+    line.extend(name.map(String::from));
+    if let Err(e) = writeln!(out, "{}", line.join(" ")) {
+        error!("write error => {}", e);
+    }
+}
+
+/// Why a file wasn't counted through
+enum Failure {
+    /// it couldn't be opened, already reported; directories are counted as empty
+    Open(Option<Counts>),
+    /// reading it stopped short, with what was counted up to there
+    Read(io::Error, Counts),
+}
+
+/// Counts `file`. Directories are reported and counted as empty, as gnu does.
+fn count_file(file: &str, opt: &Opt, scan: Scan) -> Result<Counts, Failure> {
+    let mut f = util::file::new(file.to_string());
+    if let Err(e) = f.prepare(0) {
+        info!("error found while preparing file {} => {}", file, e);
+        eprintln!("wc: {}: {}", file, util::error::describe(&e));
+        return match e.kind() {
+            io::ErrorKind::IsADirectory => Err(Failure::Open(Some(Counts::default()))),
+            _ => Err(Failure::Open(None)),
+        };
+    }
+    // the size of regular files is enough when only bytes are asked for,
+    // procfs files report none and are read through
+    let bytes_only = !(opt.lines || opt.words || opt.chars || opt.max_line_length);
+    if bytes_only && !f.is_stdin() {
+        if let Ok(len) = f.opened_len() {
+            let regular = fs::metadata(file).map(|md| md.is_file()).unwrap_or(false);
+            if regular && len > 0 {
+                return Ok(Counts { bytes: len, ..Counts::default() });
+            }
+        }
+    }
+    let mut counter = Counter::new(scan);
+    if let Err(e) = f.copy_to(&mut counter, None) {
+        error!("count error => {}", e);
+    }
+    match f.take_read_error() {
+        Some(e) => Err(Failure::Read(e, counter.finish())),
+        None => Ok(counter.finish()),
+    }
+}
+
+fn main() {
+    let mut opt = Opt::from_args();
+    opt.initialize();
+    env_logger::init();
+    let mut exit_code = 0;
+    let mut width_files = opt.files.clone();
+    let files = match &opt.files0_from {
+        Some(from) => {
+            if let Some(extra) = opt.files.first() {
+                eprintln!("wc: extra operand '{}'", extra);
+                eprintln!("file operands cannot be combined with --files0-from");
+                eprintln!("Try 'wc --help' for more information.");
+                process::exit(1);
+            }
+            let names = match read_files0(from) {
+                Ok(n) => n,
+                Err(e) => {
+                    eprintln!("wc: cannot open '{}' for reading: {}", from, util::error::describe(&e));
+                    process::exit(1);
+                },
+            };
+            // gnu only looks names up beforehand when they come from a regular file
+            let listing = if from == "-" { "/dev/stdin" } else { from.as_str() };
+            let listed_in_file = fs::metadata(listing).map(|md| md.is_file()).unwrap_or(false);
+            width_files = if listed_in_file { names.clone() } else { vec![] };
+            names
+        },
+        None => opt.files.clone(),
+    };
+    let width = match opt.files0_from {
+        Some(_) if width_files.is_empty() => 1,
+        _ if files.is_empty() => number_width(&["-".to_string()], opt.printed()),
+        _ => number_width(&width_files, opt.printed()),
+    };
+    let scan = match (opt.words || opt.chars || opt.max_line_length, utf8_locale()) {
+        (false, _) => Scan::Lines,
+        (true, false) => Scan::Bytes,
+        (true, true) => Scan::Chars,
+    };
+    let mut out = util::output::stdout(util::output::Buffering::pick(false, false));
+    if files.is_empty() && opt.files0_from.is_none() {
+        match count_file("-", &opt, scan) {
+            Ok(counts) => print_counts(&counts, None, &opt, width, &mut out),
+            Err(Failure::Read(e, counts)) => {
+                eprintln!("wc: 'standard input': {}", util::error::describe(&e));
+                print_counts(&counts, None, &opt, width, &mut out);
+                exit_code = 1;
+            },
+            Err(Failure::Open(_)) => exit_code = 1,
+        }
+    }
+    let from_stdin = opt.files0_from.as_deref() == Some("-");
+    let mut total = Counts::default();
+    for (n, file) in files.iter().enumerate() {
+        if file.is_empty() {
+            let from = opt.files0_from.as_deref().unwrap_or("-");
+            eprintln!("wc: {}:{}: invalid zero-length file name", from, n + 1);
+            exit_code = 1;
+            continue;
+        }
+        if from_stdin && file == "-" {
+            eprintln!("wc: when reading file names from stdin, no file name of '-' allowed");
+            exit_code = 1;
+            continue;
+        }
+        match count_file(file, &opt, scan) {
+            Ok(counts) => {
+                print_counts(&counts, Some(file), &opt, width, &mut out);
+                total += counts;
+            },
+            Err(Failure::Read(e, counts)) => {
+                eprintln!("wc: {}: {}", file, util::error::describe(&e));
+                print_counts(&counts, Some(file), &opt, width, &mut out);
+                total += counts;
+                exit_code = 1;
+            },
+            Err(Failure::Open(counts)) => {
+                if let Some(counts) = counts {
+                    print_counts(&counts, Some(file), &opt, width, &mut out);
+                }
+                exit_code = 1;
+            },
+        }
+    }
+    if files.len() > 1 {
+        print_counts(&total, Some("total"), &opt, width, &mut out);
+    }
+    if let Err(e) = out.flush() {
+        error!("write error => {}", e);
+    }
+    process::exit(exit_code);
+}
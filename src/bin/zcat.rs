@@ -0,0 +1,23 @@
+//! zcat is cat with --decompress always on, just like gnu's zcat is a
+//! wrapper around gzip -cd, it runs the cat binary sitting next to it.
+
+use std::env;
+use std::os::unix::process::CommandExt;
+use std::process::{self, Command};
+
+fn main() {
+    let cat = match env::current_exe() {
+        Ok(exe) => exe.with_file_name("cat"),
+        Err(e) => {
+            eprintln!("zcat: cannot find the cat binary: {}", e);
+            process::exit(1);
+        },
+    };
+    let e = Command::new(&cat)
+        .arg0("zcat")
+        .arg("--decompress")
+        .args(env::args_os().skip(1))
+        .exec();
+    eprintln!("zcat: cannot run {}: {}", cat.display(), e);
+    process::exit(1);
+}
@@ -0,0 +1,6 @@
+//! Code shared by every binary, each one only uses part of it
+
+#[macro_use]
+extern crate log;
+
+pub mod util;
@@ -0,0 +1,72 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Where reading a file stopped, identified by inode so a rotated or
+/// replaced file isn't resumed at someone else's offset
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub path: String,
+    pub inode: u64,
+    pub size: u64,
+    pub offset: u64,
+}
+
+impl Checkpoint {
+    fn parse(line: &str) -> Option<Checkpoint> {
+        let mut fields = line.splitn(4, '\t');
+        let inode = fields.next()?.parse().ok()?;
+        let size = fields.next()?.parse().ok()?;
+        let offset = fields.next()?.parse().ok()?;
+        let path = fields.next()?.to_string();
+        Some(Checkpoint { path, inode, size, offset })
+    }
+
+    fn format(&self) -> String {
+        format!("{}\t{}\t{}\t{}\n", self.inode, self.size, self.offset, self.path)
+    }
+}
+
+/// Checkpoints of every file read, saved as one tab separated
+/// `inode size offset path` line per file
+#[derive(Debug, Default)]
+pub struct Registry {
+    checkpoints: BTreeMap<String, Checkpoint>,
+}
+
+impl Registry {
+    /// A missing state file is an empty registry, lines it can't parse are ignored
+    pub fn load(state: &Path) -> Result<Registry, io::Error> {
+        let content = match fs::read_to_string(state) {
+            Ok(c) => c,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut registry = Registry::default();
+        for line in content.lines() {
+            match Checkpoint::parse(line) {
+                Some(c) => { registry.checkpoints.insert(c.path.clone(), c); },
+                None => warn!("ignoring invalid checkpoint => {}", line),
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Written aside and renamed over the state file so it is never left half written
+    pub fn save(&self, state: &Path) -> Result<(), io::Error> {
+        let content: String = self.checkpoints.values().map(|c| c.format()).collect();
+        let mut temporary = state.as_os_str().to_owned();
+        temporary.push(".tmp");
+        fs::write(&temporary, content)?;
+        fs::rename(&temporary, state)
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Checkpoint> {
+        self.checkpoints.get(path)
+    }
+
+    pub fn set(&mut self, checkpoint: Checkpoint) {
+        self.checkpoints.insert(checkpoint.path.clone(), checkpoint);
+    }
+}
@@ -0,0 +1,33 @@
+use std::io::{self, BufRead, BufReader};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+
+/// Longest magic number we look for
+pub const MAGIC_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+}
+
+impl Compression {
+    pub fn detect(magic: &[u8]) -> Option<Compression> {
+        if magic.starts_with(GZIP_MAGIC) { return Some(Compression::Gzip); }
+        if magic.starts_with(ZSTD_MAGIC) { return Some(Compression::Zstd); }
+        if magic.starts_with(XZ_MAGIC) { return Some(Compression::Xz); }
+        None
+    }
+
+    pub fn decoder(self, reader: Box<dyn BufRead>) -> Result<Box<dyn BufRead>, io::Error> {
+        debug!("wrapping reader on a {:?} decoder", self);
+        Ok(match self {
+            Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+            Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(reader)?)),
+            Compression::Xz => Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(reader))),
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+
+/// Whether an operand is a pattern rather than a file name, a file
+/// literally named like a pattern is still taken as a file
+pub fn is_glob(operand: &str) -> bool {
+    operand.contains(['*', '?', '[']) && fs::metadata(operand).is_err()
+}
+
+/// Finds the files matching glob operands and the files of watched
+/// directories whose names match a pattern, scanned again to pick up
+/// files created later
+#[derive(Debug)]
+pub struct Discovery {
+    dirs: Vec<PathBuf>,
+    pattern: Pattern,
+    globs: Vec<String>,
+}
+
+impl Discovery {
+    pub fn new(dirs: Vec<PathBuf>, pattern: &str, globs: Vec<String>) -> Result<Discovery, glob::PatternError> {
+        for g in globs.iter() {
+            Pattern::new(g)?;
+        }
+        Ok(Discovery { dirs, pattern: Pattern::new(pattern)?, globs })
+    }
+
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty() && self.globs.is_empty()
+    }
+
+    /// Every file found right now, sorted and without repetitions,
+    /// directories that can't be read are skipped
+    pub fn scan(&self) -> Vec<String> {
+        let mut found = Vec::new();
+        for dir in self.dirs.iter() {
+            if let Err(e) = self.scan_dir(dir, &mut found) {
+                warn!("not able to scan {} => {}", dir.display(), e);
+            }
+        }
+        for g in self.globs.iter() {
+            // checked by new, an invalid pattern can't show up here
+            for path in glob::glob(g).expect("valid pattern").flatten() {
+                if path.is_file() {
+                    found.push(path.to_string_lossy().to_string());
+                }
+            }
+        }
+        found.sort();
+        found.dedup();
+        trace!("scan found => {:?}", found);
+        found
+    }
+
+    fn scan_dir(&self, dir: &Path, found: &mut Vec<String>) -> Result<(), io::Error> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let matches = entry.file_name().to_str().is_some_and(|n| self.pattern.matches(n));
+            if matches && entry.file_type()?.is_file() {
+                found.push(entry.path().to_string_lossy().to_string());
+            }
+        }
+        Ok(())
+    }
+}
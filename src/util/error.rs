@@ -0,0 +1,11 @@
+use std::io;
+
+/// Describes an io::Error the way gnu-coreutils does, without the
+/// " (os error N)" suffix rust appends to system errors.
+pub fn describe(e: &io::Error) -> String {
+    let msg = e.to_string();
+    match msg.find(" (os error ") {
+        Some(i) => msg[..i].to_string(),
+        None => msg,
+    }
+}
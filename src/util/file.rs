@@ -0,0 +1,869 @@
+use std::io::{BufRead, BufReader, self, Read, Seek, Write};
+use std::env;
+use std::fs::{self, File, OpenOptions, metadata};
+use std::os::unix::fs::{FileExt, MetadataExt};
+
+use std::collections::VecDeque;
+use std::process;
+
+use super::checkpoint::Checkpoint;
+use super::compression::{self, Compression};
+use super::mapping::{self, Map};
+
+/// Regular files from this size on are memory mapped instead of buffered
+pub const MMAP_THRESHOLD: u64 = 64 * 1024;
+
+/// Buffer size used on streams, data skipped on them is discarded in blocks this big
+const STREAM_BUFFER: usize = 64 * 1024;
+
+/// Mapped bytes are copied out this much at a time before being written,
+/// so nothing read past the end of a truncated file makes it out
+const MAPPED_WINDOW: usize = 1024 * 1024;
+
+/// What a FileDetail reads from, only plain files are able to seek
+enum Input {
+    File(BufReader<File>),
+    Stream(Box<dyn BufRead>),
+    /// bytes put back, read before the input they were taken from
+    Unread(io::Cursor<Vec<u8>>, Box<Input>),
+}
+
+/// Whether some of the bytes put back are still to be read
+fn pending(front: &io::Cursor<Vec<u8>>) -> bool {
+    (front.position() as usize) < front.get_ref().len()
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(r) => r.read(buf),
+            Input::Stream(r) => r.read(buf),
+            Input::Unread(front, _) if pending(front) => front.read(buf),
+            Input::Unread(_, rest) => rest.read(buf),
+        }
+    }
+}
+
+impl BufRead for Input {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Input::File(r) => r.fill_buf(),
+            Input::Stream(r) => r.fill_buf(),
+            Input::Unread(front, _) if pending(front) => front.fill_buf(),
+            Input::Unread(_, rest) => rest.fill_buf(),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Input::File(r) => r.consume(amt),
+            Input::Stream(r) => r.consume(amt),
+            Input::Unread(front, _) if pending(front) => front.consume(amt),
+            Input::Unread(_, rest) => rest.consume(amt),
+        }
+    }
+}
+
+/// What a file looks like from the outside, any change in it is worth
+/// a look: network filesystems don't report every write to inotify and
+/// a file rewritten in place keeps its size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stamp {
+    pub size: u64,
+    /// modification time in nanoseconds
+    pub mtime: i128,
+    pub inode: u64,
+}
+
+pub fn new(path: String) -> FileDetail {
+    FileDetail{
+        path,
+        is_text: true,
+        bufread: Input::Stream(Box::new(io::empty())),
+        bufpos: 0,
+        decompress: false,
+        compression: None,
+        mapped: None,
+        inode: None,
+        read_error: None,
+    }
+}
+
+pub struct FileDetail {
+    path: String,
+    is_text: bool,
+    bufread: Input,
+    bufpos: usize,
+    decompress: bool,
+    compression: Option<Compression>,
+    /// the opened file stays in bufread, it is read from there once the map is left
+    mapped: Option<Map>,
+    inode: Option<u64>,
+    /// what ended the last read, read errors end it as EOF does
+    read_error: Option<io::Error>,
+}
+
+impl FileDetail {
+    fn is_dir(&self) -> Result<bool, io::Error> {
+        let md = metadata(self.path.clone())?;
+        trace!("Is {} a dir? {}", self.path, md.is_dir());
+        Ok(md.is_dir())
+    }
+    
+    fn open_buffer(&mut self, start_pos: usize) -> Result<(), io::Error> {
+        if !self.is_stdin() && self.is_dir()? {
+            warn!("open_buffer => {} is dir", self.path.clone());
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, "Is a directory"));
+        }
+        self.compression = None;
+        self.mapped = None;
+        self.inode = None;
+        self.read_error = None;
+        self.bufread = match self.path.as_ref() {
+            "-" => {
+                info!("opening stdin");
+                let mut reader = BufReader::with_capacity(STREAM_BUFFER, io::stdin());
+                if self.decompress {
+                    self.compression = Compression::detect(reader.fill_buf()?);
+                }
+                Input::Stream(Box::new(reader))
+            },
+            _ => {
+                let mut opened_file = BufReader::new(File::open(self.path.clone())?);
+                self.inode = opened_file.get_ref().metadata().ok().map(|md| md.ino());
+                if self.decompress {
+                    self.compression = Compression::detect(opened_file.fill_buf()?);
+                }
+                if self.compression.is_none() {
+                    if let Some(map) = self.map_file(opened_file.get_ref()) {
+                        info!("{} is memory mapped", self.path);
+                        self.bufpos = start_pos.min(map.len());
+                        self.mapped = Some(map);
+                        self.bufread = Input::File(opened_file);
+                        return Ok(());
+                    }
+                    self.bufpos = match opened_file.seek(std::io::SeekFrom::Start(start_pos as u64)) {
+                        Ok(p) => p as usize,
+                        Err(e) => {
+                            error!("open_buffer, not able to seek: {}", e);
+                            0
+                        },
+                    };
+                }
+                Input::File(opened_file)
+            },
+        };
+        if let Some(c) = self.compression {
+            info!("{} is {:?} compressed", self.path, c);
+            let reader: Box<dyn BufRead> = match std::mem::replace(&mut self.bufread, Input::Stream(Box::new(io::empty()))) {
+                Input::File(r) => Box::new(r),
+                Input::Stream(r) => r,
+                unread => Box::new(unread),
+            };
+            self.bufread = Input::Stream(c.decoder(reader)?);
+        }
+        Ok(())
+    }
+
+    /// Maps large regular files in memory, None means the file has to be
+    /// buffered: pipes, procfs files reporting no size, small files and
+    /// files changing size while being mapped.
+    fn map_file(&self, file: &File) -> Option<Map> {
+        let md = file.metadata().ok()?;
+        if !md.is_file() || md.len() < MMAP_THRESHOLD {
+            return None;
+        }
+        let map = match mapping::map(file) {
+            Ok(m) => m,
+            Err(e) => {
+                debug!("map_file, not able to map {} => {}", self.path, e);
+                return None;
+            },
+        };
+        let current_len = file.metadata().ok()?.len();
+        if current_len != map.len() as u64 {
+            debug!("map_file, {} changed size while mapping", self.path);
+            return None;
+        }
+        Some(map)
+    }
+
+    /// Called when the map is read through, if the file changed size in
+    /// the meantime it is read by a buffer from the current position on
+    fn mapped_eof(&mut self) -> Result<(), io::Error> {
+        let map_len = match &self.mapped {
+            Some(m) => m.len() as u64,
+            None => return Ok(()),
+        };
+        let current_len = match &self.bufread {
+            Input::File(r) => r.get_ref().metadata()?.len(),
+            Input::Stream(_) | Input::Unread(..) => map_len,
+        };
+        if current_len != map_len {
+            info!("{} changed size from {} to {} while mapped, falling back to a buffer", self.path, map_len, current_len);
+            self.unmap()?;
+        }
+        Ok(())
+    }
+
+    /// Leaves the map for the opened file, read by a buffer from the current position on
+    fn unmap(&mut self) -> Result<(), io::Error> {
+        self.mapped = None;
+        if let Input::File(r) = &mut self.bufread {
+            r.seek(io::SeekFrom::Start(self.bufpos as u64))?;
+        }
+        Ok(())
+    }
+
+    /// Runs `read` on the mapped bytes not read yet, it hands back how many
+    /// it went through. None when the file isn't mapped or got truncated
+    /// under the read: what it got is thrown away and the file is left to
+    /// the buffer from the current position on, for the caller to read.
+    fn mapped_read<T>(&mut self, read: impl FnOnce(&[u8]) -> (usize, T)) -> Option<T> {
+        let (n, result) = read(self.mapped_remaining()?);
+        if self.mapped.as_ref().is_some_and(|m| m.is_faulted()) {
+            info!("{} was truncated while mapped, falling back to a buffer", self.path);
+            if let Err(e) = self.unmap() {
+                warn!("unmap error => {}", e);
+            }
+            return None;
+        }
+        self.bufpos += n;
+        Some(result)
+    }
+
+    /// The mapped bytes not read yet, None when the file isn't mapped
+    fn mapped_remaining(&mut self) -> Option<&[u8]> {
+        let exhausted = match &self.mapped {
+            Some(m) => self.bufpos >= m.len(),
+            None => return None,
+        };
+        if exhausted {
+            if let Err(e) = self.mapped_eof() {
+                warn!("mapped_eof error => {}", e);
+            }
+        }
+        let bufpos = self.bufpos;
+        self.mapped.as_ref().map(|m| &m[bufpos.min(m.len())..])
+    }
+
+    /// Peeks the magic bytes of a regular file looking for a known compression
+    fn sniff(&self) -> Result<Option<Compression>, io::Error> {
+        let mut magic = Vec::with_capacity(compression::MAGIC_LEN);
+        File::open(self.path.clone())?
+            .take(compression::MAGIC_LEN as u64)
+            .read_to_end(&mut magic)?;
+        Ok(Compression::detect(&magic))
+    }
+
+    pub fn prepare(&mut self, start_pos: usize) -> Result<(), io::Error> {
+        self.open_buffer(start_pos)
+    }
+
+    /// Transparently decompress gzip, zstd and xz inputs, detected by their magic bytes
+    pub fn set_decompress(&mut self, decompress: bool) {
+        self.decompress = decompress;
+    }
+
+    /// Whether prepare is able to seek straight to the start position,
+    /// compressed files and stdin have to be read through instead
+    pub fn is_seekable(&self) -> bool {
+        if self.is_stdin() {
+            return false;
+        }
+        if self.decompress {
+            return match self.sniff() {
+                Ok(c) => c.is_none(),
+                Err(e) => {
+                    debug!("is_seekable, not able to sniff {} => {}", self.path, e);
+                    true
+                },
+            };
+        }
+        true
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compression.is_some()
+    }
+    
+    pub fn is_mapped(&self) -> bool {
+        self.mapped.is_some()
+    }
+    
+    /// Reads a line as text, a line that isn't valid UTF-8 is left unread
+    /// and None returned with is_text false, so the raw readers pick it up
+    /// from the very first byte and text reading can go on after it
+    pub fn read_line(&mut self) -> Option<String> {
+        let line = self.read_line_bytes()?;
+        match String::from_utf8(line) {
+            Ok(l) => {
+                trace!("returning some line");
+                self.is_text = true;
+                Some(l)
+            },
+            Err(e) => {
+                debug!("read_line error => {}", e);
+                self.is_text = false;
+                self.unread(e.into_bytes());
+                None
+            },
+        }
+    }
+
+    /// Reads a line as it is, new line included when there is one
+    pub fn read_line_bytes(&mut self) -> Option<Vec<u8>> {
+        trace!("reading by line");
+        let mapped = self.mapped_read(|data| {
+            let end = memchr::memchr(b'\n', data).map_or(data.len(), |i| i + 1);
+            (end, data[..end].to_vec())
+        });
+        if let Some(line) = mapped {
+            return if line.is_empty() { None } else { Some(line) };
+        }
+        let mut _line = Vec::new();
+        match self.bufread.read_until(b'\n', &mut _line) {
+            Ok(i) => {
+                debug!("read_line lenght => {}", i);
+                self.bufpos += i;
+                if i == 0 {
+                    return None;
+                }
+            },
+            Err(e) => {
+                // whatever was read before the error was consumed anyway, it is the last line
+                debug!("read_line error => {}", e);
+                self.bufpos += _line.len();
+                self.read_error = Some(e);
+                if _line.is_empty() {
+                    return None;
+                }
+            }
+        }
+        Some(_line)
+    }
+
+    /// Puts back a line just read: the map and plain files step back,
+    /// streams get it in front of what is still buffered, next to what
+    /// was put back before rather than wrapping the stream once more
+    fn unread(&mut self, line: Vec<u8>) {
+        let len = line.len();
+        if self.is_mapped() {
+            self.bufpos -= len;
+            return;
+        }
+        if let Input::File(r) = &mut self.bufread {
+            if r.seek_relative(-(len as i64)).is_ok() {
+                self.bufpos -= len;
+                return;
+            }
+            warn!("unread, not able to seek back {} bytes", len);
+        }
+        let rest = std::mem::replace(&mut self.bufread, Input::Stream(Box::new(io::empty())));
+        self.bufread = match rest {
+            Input::Unread(front, rest) => {
+                let mut line = line;
+                line.extend_from_slice(&front.get_ref()[front.position() as usize..]);
+                Input::Unread(io::Cursor::new(line), rest)
+            },
+            rest => Input::Unread(io::Cursor::new(line), Box::new(rest)),
+        };
+        self.bufpos -= len;
+    }
+
+    /// Reads up to `lenght` bytes with a single read, so a pipe may hand
+    /// back less than asked; None on EOF
+    pub fn read_chunk(&mut self, lenght: usize) -> Option<Vec<u8>> {
+        debug!("reading by chunk size => {}", lenght);
+        let mapped = self.mapped_read(|data| {
+            let n = lenght.min(data.len());
+            (n, data[..n].to_vec())
+        });
+        if let Some(chunk) = mapped {
+            return if chunk.is_empty() { None } else { Some(chunk) };
+        }
+        let mut buffer = vec![0u8; lenght];
+        loop {
+            match self.bufread.read(buffer.as_mut_slice()) {
+                Ok(n) => {
+                    trace!("read chunk of size => {}", n);
+                    self.bufpos += n;
+                    if n == 0 {
+                        trace!("empty chunk found, returning None");
+                        return None;
+                    }
+                    buffer.truncate(n);
+                    return Some(buffer);
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    trace!("nothing to read yet");
+                    return None;
+                },
+                Err(e) => {
+                    warn!("read chunk error => {}", e);
+                    self.read_error = Some(e);
+                    return None;
+                },
+            };
+        }
+    }
+
+    /// Streams whatever is left, up to `limit` bytes when given, into
+    /// `writer` a buffer at a time; returns how many bytes were copied
+    pub fn copy_to(&mut self, writer: &mut dyn Write, limit: Option<u64>) -> Result<u64, io::Error> {
+        let mut copied: u64 = 0;
+        let mut window = Vec::new();
+        loop {
+            let wanted = match limit {
+                Some(l) if copied >= l => break,
+                Some(l) => (l - copied).min(usize::MAX as u64) as usize,
+                None => usize::MAX,
+            };
+            let mapped = self.mapped_read(|data| {
+                let n = wanted.min(data.len()).min(MAPPED_WINDOW);
+                window.clear();
+                window.extend_from_slice(&data[..n]);
+                (n, n)
+            });
+            let n = match mapped {
+                Some(0) => break,
+                Some(n) => {
+                    writer.write_all(&window)?;
+                    n
+                },
+                None => match self.fill_buf() {
+                    Some(b) => {
+                        let n = wanted.min(b.len());
+                        writer.write_all(&b[..n])?;
+                        self.bufread.consume(n);
+                        self.bufpos += n;
+                        n
+                    },
+                    None => break,
+                },
+            };
+            copied += n as u64;
+        }
+        trace!("copied {} bytes from {}", copied, self.path);
+        Ok(copied)
+    }
+
+    /// Streams up to `lines` lines, the last one may lack its new line,
+    /// into `writer` as they are; returns how many bytes were copied
+    pub fn copy_lines_to(&mut self, writer: &mut dyn Write, lines: u64) -> Result<u64, io::Error> {
+        let mut copied: u64 = 0;
+        let mut remaining = lines;
+        let mut window = Vec::new();
+        while remaining > 0 {
+            let mapped = self.mapped_read(|data| {
+                let (n, found) = lines_end(&data[..data.len().min(MAPPED_WINDOW)], remaining);
+                window.clear();
+                window.extend_from_slice(&data[..n]);
+                (n, (n, found))
+            });
+            let (n, found) = match mapped {
+                Some((0, _)) => break,
+                Some((n, found)) => {
+                    writer.write_all(&window)?;
+                    (n, found)
+                },
+                None => match self.fill_buf() {
+                    Some(b) => {
+                        let (n, found) = lines_end(b, remaining);
+                        writer.write_all(&b[..n])?;
+                        self.bufread.consume(n);
+                        self.bufpos += n;
+                        (n, found)
+                    },
+                    None => break,
+                },
+            };
+            copied += n as u64;
+            remaining -= found;
+        }
+        trace!("copied {} bytes from {}", copied, self.path);
+        Ok(copied)
+    }
+
+    pub fn is_text(&self) -> bool {
+        self.is_text
+    }
+
+    /// The error a read stopped at, None when reads only ever ran into EOF
+    pub fn take_read_error(&mut self) -> Option<io::Error> {
+        self.read_error.take()
+    }
+
+    /// Skips `bytes` bytes, seeking on plain files and discarding
+    /// whole buffers on streams
+    pub fn walk_buffer_bytes(&mut self, bytes: usize) {
+        trace!("walking buffer bytes => {} - {}", bytes, self.path.clone());
+        if let Some(data) = self.mapped_remaining() {
+            self.bufpos += bytes.min(data.len());
+            return;
+        }
+        if let Input::File(r) = &mut self.bufread {
+            // seeking goes past the end as gladly as anywhere, procfs files
+            // reporting no size are read through like streams instead
+            let len = r.get_ref().metadata().map(|md| md.len() as usize).unwrap_or(0);
+            if len > 0 {
+                let target = (self.bufpos + bytes).min(len).max(self.bufpos);
+                match r.seek(io::SeekFrom::Start(target as u64)) {
+                    Ok(p) => self.bufpos = p as usize,
+                    Err(e) => warn!("walk_buffer_bytes, not able to seek => {}", e),
+                }
+                return;
+            }
+        }
+        let mut remaining = bytes;
+        while remaining > 0 {
+            let available = match self.fill_buf() {
+                Some(b) => b.len(),
+                None => break,
+            };
+            let n = available.min(remaining);
+            self.bufread.consume(n);
+            self.bufpos += n;
+            remaining -= n;
+        }
+    }
+
+    /// Skips `lines` lines looking for new lines a whole buffer at a time,
+    /// returns how many there were
+    pub fn walk_buffer_lines(&mut self, lines: usize) -> usize {
+        if lines == 0 {
+            return 0;
+        }
+        let mapped = self.mapped_read(|data| match memchr::memchr_iter(b'\n', data).nth(lines - 1) {
+            Some(i) => (i + 1, lines),
+            None => (data.len(), memchr::memchr_iter(b'\n', data).count()),
+        });
+        if let Some(walked) = mapped {
+            return walked;
+        }
+        let mut remaining = lines;
+        while remaining > 0 {
+            let (n, found) = match self.fill_buf() {
+                Some(b) => {
+                    let new_lines = memchr::memchr_iter(b'\n', b).count();
+                    if new_lines >= remaining {
+                        (memchr::memchr_iter(b'\n', b).nth(remaining - 1).map_or(b.len(), |i| i + 1), new_lines)
+                    } else {
+                        (b.len(), new_lines)
+                    }
+                },
+                None => break,
+            };
+            self.bufread.consume(n);
+            self.bufpos += n;
+            remaining -= found.min(remaining);
+        }
+        lines - remaining
+    }
+
+    /// The buffered bytes not consumed yet, None on EOF or read errors
+    fn fill_buf(&mut self) -> Option<&[u8]> {
+        loop {
+            match self.bufread.fill_buf() {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    warn!("fill_buf error => {}", e);
+                    self.read_error = Some(e);
+                    return None;
+                },
+                Ok(_) => break,
+            }
+        }
+        match self.bufread.fill_buf() {
+            Ok([]) | Err(_) => None,
+            Ok(b) => Some(b),
+        }
+    }
+
+    pub fn last_bytes(&mut self, bytes: usize) -> (usize, Vec<u8>) {
+        let mapped = self.mapped_read(|data| {
+            let size = data.len();
+            (size, (size, data[size - bytes.min(size)..].to_vec()))
+        });
+        if let Some(last) = mapped {
+            return last;
+        }
+        let mut bunch: VecDeque<u8> = VecDeque::with_capacity(bytes);
+        let mut size = 0;
+        while let Some(b) = self.fill_buf() {
+            let n = b.len();
+            // only the tail of each buffer can make it to the result
+            bunch.extend(&b[n - bytes.min(n)..]);
+            while bunch.len() > bytes {
+                bunch.pop_front();
+            }
+            self.bufread.consume(n);
+            self.bufpos += n;
+            size += n;
+        }
+        (size, bunch.into_iter().collect())
+    }
+
+    pub fn last_lines(&mut self, lines: usize) -> (usize, Vec<Vec<u8>>) {
+        let mapped = self.mapped_read(|data| {
+            let newlines = memchr::memchr_iter(b'\n', data).count();
+            let size = if data.is_empty() || data.ends_with(b"\n") { newlines } else { newlines + 1 };
+            let start = match lines {
+                0 => data.len(),
+                _ => {
+                    let search_end = if data.ends_with(b"\n") { data.len() - 1 } else { data.len() };
+                    memchr::memrchr_iter(b'\n', &data[..search_end]).nth(lines - 1).map_or(0, |i| i + 1)
+                },
+            };
+            let bunch = data[start..].split_inclusive(|b| *b == b'\n').map(|l| l.to_vec()).collect();
+            (data.len(), (size, bunch))
+        });
+        if let Some(last) = mapped {
+            return last;
+        }
+        let mut bunch: VecDeque<Vec<u8>> = VecDeque::with_capacity(lines);
+        let mut size = 0;
+        while let Some(b) = self.read_line_bytes() {
+            bunch.push_back(b);
+            size += 1;
+            if bunch.len() > lines {
+                bunch.pop_front();
+            }
+        }
+        (size, bunch.into_iter().collect())
+    }
+
+    pub fn path(&self) -> String {
+        self.path.clone()
+    }
+
+    #[allow(clippy::len_without_is_empty)] // the size on disk, not the length of a collection
+    pub fn len(&self) -> Result<u64, io::Error> {
+        if self.is_stdin() {
+            warn!("len => {} is stdin", self.path.clone());
+            return Err(io::Error::other("STDIN has no lenght"));
+        }
+        let md = metadata(self.path.clone())?;
+        Ok(md.len())
+    }
+
+    /// Size, modification time and inode of the file the path names now
+    pub fn stamp(&self) -> Result<Stamp, io::Error> {
+        if self.is_stdin() {
+            return Err(io::Error::other("STDIN has no stamp"));
+        }
+        let md = metadata(self.path.clone())?;
+        Ok(Stamp {
+            size: md.len(),
+            mtime: md.mtime() as i128 * 1_000_000_000 + md.mtime_nsec() as i128,
+            inode: md.ino(),
+        })
+    }
+
+    /// Up to `len` bytes of the opened file ending at `offset`, read without
+    /// moving the position, None on inputs that can't be read at an offset
+    pub fn bytes_before(&self, offset: u64, len: usize) -> Option<Vec<u8>> {
+        let start = offset.saturating_sub(len as u64);
+        self.read_at(start, (offset - start) as usize)
+    }
+
+    /// Up to `len` bytes of the opened file from `offset` on, read without
+    /// moving the position, None on inputs that can't be read at an offset
+    pub fn read_at(&self, offset: u64, len: usize) -> Option<Vec<u8>> {
+        if let Some(m) = &self.mapped {
+            let start = (offset as usize).min(m.len());
+            let bytes = m[start..(start + len).min(m.len())].to_vec();
+            // once truncated the file is read at the offset instead
+            if !m.is_faulted() {
+                return Some(bytes);
+            }
+        }
+        match &self.bufread {
+            Input::File(r) if self.compression.is_none() => {
+                let mut buffer = vec![0u8; len];
+                let mut read = 0;
+                while read < buffer.len() {
+                    match r.get_ref().read_at(&mut buffer[read..], offset + read as u64) {
+                        Ok(0) => break,
+                        Ok(n) => read += n,
+                        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+                        Err(e) => {
+                            debug!("read_at, not able to read {} => {}", self.path, e);
+                            return None;
+                        },
+                    }
+                }
+                buffer.truncate(read);
+                Some(buffer)
+            },
+            _ => None,
+        }
+    }
+
+    /// The line going on at `offset` up to its new line included, empty at the end of the file
+    fn line_at(&self, offset: u64) -> Option<Vec<u8>> {
+        let mut line = Vec::new();
+        loop {
+            let block = self.read_at(offset + line.len() as u64, 4096)?;
+            if let Some(end) = memchr::memchr(b'\n', &block) {
+                line.extend_from_slice(&block[..=end]);
+                return Some(line);
+            }
+            if block.is_empty() {
+                return Some(line);
+            }
+            line.extend_from_slice(&block);
+        }
+    }
+
+    /// Offset of the first line `found` says yes to, on lines sorted so it
+    /// says no up to some line and yes from there on, lines it has no answer
+    /// for are skipped. Lines are read where the binary search lands without
+    /// moving the position, None on inputs that can't be read at an offset.
+    pub fn bisect_lines(&self, found: impl Fn(&[u8]) -> Option<bool>) -> Option<u64> {
+        // the first line from `start` on found has an answer for, before `end`
+        let answered = |start: u64, end: u64| -> Option<Option<(u64, u64, bool)>> {
+            let mut offset = start;
+            while offset < end {
+                let line = self.line_at(offset)?;
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(yes) = found(&line) {
+                    return Some(Some((offset, offset + line.len() as u64, yes)));
+                }
+                offset += line.len() as u64;
+            }
+            Some(None)
+        };
+        let len = match &self.mapped {
+            Some(m) => m.len() as u64,
+            None => self.len().ok()?,
+        };
+        // every answered line before lo says no, the first one from hi on says yes
+        let (mut lo, mut hi) = (0, len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let start = if mid == lo { lo } else { mid - 1 + self.line_at(mid - 1)?.len() as u64 };
+            let start = if start >= hi { lo } else { start };
+            match answered(start, hi)? {
+                Some((_, end, false)) => lo = end,
+                Some((line, _, true)) => hi = line,
+                None => hi = start,
+            }
+        }
+        Some(answered(lo, len)?.map_or(len, |(line, _, _)| line))
+    }
+
+    /// Inode of the opened file, None for stdin or before prepare
+    pub fn inode(&self) -> Option<u64> {
+        self.inode
+    }
+
+    /// Copies what is left to read into a temporary file read instead, for
+    /// inputs that can't be read at an offset: stdin, pipes, compressed
+    /// files and procfs files reporting no size. The file is unlinked
+    /// right away, it is gone once dropped.
+    pub fn spill(&mut self) -> Result<(), io::Error> {
+        if self.mapped.is_some() {
+            return Ok(());
+        }
+        if let Input::File(r) = &self.bufread {
+            let md = r.get_ref().metadata()?;
+            if self.compression.is_none() && md.is_file() && md.len() > 0 {
+                return Ok(());
+            }
+        }
+        let mut spilled = temporary()?;
+        let size = io::copy(&mut self.bufread, &mut spilled)?;
+        info!("{} spilled {} bytes to a temporary file", self.path, size);
+        spilled.seek(io::SeekFrom::Start(0))?;
+        self.bufread = Input::File(BufReader::new(spilled));
+        self.compression = None;
+        self.bufpos = 0;
+        Ok(())
+    }
+
+    /// Size of what is opened, the temporary file once spilled
+    pub fn opened_len(&self) -> Result<u64, io::Error> {
+        if let Some(m) = &self.mapped {
+            return Ok(m.len() as u64);
+        }
+        match &self.bufread {
+            Input::File(r) => Ok(r.get_ref().metadata()?.len()),
+            Input::Stream(_) | Input::Unread(..) => Err(io::Error::other("a stream has no length")),
+        }
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        self.path == "-"
+    }
+
+    /// Offset of the next byte to be read, on compressed inputs
+    /// it is counted on the decompressed data
+    pub fn position(&self) -> u64 {
+        self.bufpos as u64
+    }
+
+    /// Where reading stopped, None for inputs that can't be reopened at an
+    /// offset: stdin and compressed files
+    pub fn checkpoint(&self) -> Option<Checkpoint> {
+        if self.is_stdin() || self.is_compressed() {
+            return None;
+        }
+        let inode = self.inode?;
+        let md = metadata(self.path.clone()).ok()?;
+        if md.ino() != inode {
+            debug!("checkpoint, {} was replaced while reading", self.path);
+            return None;
+        }
+        Some(Checkpoint {
+            path: self.path.clone(),
+            inode,
+            size: md.len(),
+            offset: self.position(),
+        })
+    }
+
+    /// The offset to reopen the file at, None when it was replaced by
+    /// another file or truncated below the checkpoint since it was taken
+    pub fn resume_offset(&self, checkpoint: &Checkpoint) -> Option<usize> {
+        if self.is_stdin() {
+            return None;
+        }
+        let md = metadata(self.path.clone()).ok()?;
+        if md.ino() != checkpoint.inode || md.len() < checkpoint.offset {
+            info!("{} can't be resumed from {:?}", self.path, checkpoint);
+            return None;
+        }
+        Some(checkpoint.offset as usize)
+    }
+}
+
+/// Where the `lines`th line of `data` ends, the whole of it when there are
+/// fewer, and how many lines were complete
+fn lines_end(data: &[u8], lines: u64) -> (usize, u64) {
+    let nth = (lines - 1).min(usize::MAX as u64) as usize;
+    match memchr::memchr_iter(b'\n', data).nth(nth) {
+        Some(i) => (i + 1, lines),
+        None => (data.len(), memchr::memchr_iter(b'\n', data).count() as u64),
+    }
+}
+
+/// A file in the temporary directory, unlinked as soon as it is created
+fn temporary() -> Result<File, io::Error> {
+    let dir = env::temp_dir();
+    let mut n = 0;
+    loop {
+        let path = dir.join(format!(".rust-core-utils-{}-{}", process::id(), n));
+        match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+            Ok(file) => {
+                fs::remove_file(&path)?;
+                return Ok(file);
+            },
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
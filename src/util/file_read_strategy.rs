@@ -0,0 +1,53 @@
+
+#[derive(Debug, Clone)]
+pub enum FileReadStrategy {
+    FromByte(usize),
+    LastBytes(usize),
+    FromLine(usize),
+    LastLines(usize),
+    /// from the first line stamped at or after --since
+    Since,
+    None(String),
+}
+
+impl FileReadStrategy {
+    /// Takes -c over -n, a count left out gives the last 10 lines while
+    /// an explicit 0 gives nothing
+    pub fn pick(bytes: Option<String>, lines: Option<String>) -> FileReadStrategy {
+        if let Some(bytes) = bytes {
+            return match FileReadStrategy::from_last_converter(&bytes) {
+                // +K is one based, the strategies hold how much is skipped
+                Some((true, from)) => FileReadStrategy::FromByte(from.saturating_sub(1)),
+                Some((false, last)) => FileReadStrategy::LastBytes(last),
+                None => {
+                    let error_msg = format!("invalid number of bytes: '{}'", bytes);
+                    debug!("FileReadStrategy - Initialize error => {}", error_msg);
+                    FileReadStrategy::None(error_msg)
+                },
+            };
+        }
+        if let Some(lines) = lines {
+            return match FileReadStrategy::from_last_converter(&lines) {
+                Some((true, from)) => FileReadStrategy::FromLine(from.saturating_sub(1)),
+                Some((false, last)) => FileReadStrategy::LastLines(last),
+                None => {
+                    let error_msg = format!("invalid number of lines: '{}'", lines);
+                    debug!("FileReadStrategy - Initialize error => {}", error_msg);
+                    FileReadStrategy::None(error_msg)
+                },
+            };
+        }
+        // default
+        FileReadStrategy::LastLines(10)
+    }
+
+    /// Whether the count is taken from the start, +K, and the count itself
+    fn from_last_converter(size: &str) -> Option<(bool, usize)> {
+        let parsed = match size.strip_prefix('-').unwrap_or(size).parse::<usize>() {
+            Ok(p) => p,
+            Err(_) => return None,
+        };
+        Some((size.starts_with('+'), parsed))
+    }
+
+}
@@ -0,0 +1,169 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CString, OsStr};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Something happened to the file at `path`
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub path: PathBuf,
+    /// the file was created or moved in
+    pub created: bool,
+}
+
+/// Watches directories rather than files, a directory reports changes
+/// to every file in it and still does once a file is rotated
+pub struct Inotify {
+    fd: RawFd,
+    dirs: HashMap<PathBuf, i32>,
+    watches: HashMap<i32, PathBuf>,
+    /// directories left to polling, looked up once
+    unreliable: HashSet<PathBuf>,
+}
+
+/// Filesystems whose files change without inotify hearing of it, changes
+/// made by other hosts never reach the local kernel: nfs, smb, cifs, smb2,
+/// fuse, coda, afs, 9p, ceph, gfs, ocfs2 and lustre
+const REMOTE_MAGICS: &[u32] = &[
+    0x6969, 0x517B, 0xFF53_4D42, 0xFE53_4D42, 0x6573_5546, 0x7375_7245,
+    0x5346_414F, 0x0102_1997, 0x00C3_6400, 0x0116_1970, 0x7461_636F, 0x0BD0_0BD0,
+];
+
+/// Whether files on the filesystem of statfs type `magic` may change
+/// without inotify hearing of it
+pub fn is_remote(magic: u32) -> bool {
+    REMOTE_MAGICS.contains(&magic)
+}
+
+/// The statfs type of the filesystem `dir` is on, None when it can't be looked at
+pub fn filesystem_magic(dir: &Path) -> Option<u32> {
+    let target = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let path = CString::new(target.as_os_str().as_bytes()).ok()?;
+    let mut fs: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut fs) } < 0 {
+        trace!("not able to statfs {} => {}", target.display(), io::Error::last_os_error());
+        return None;
+    }
+    let magic = fs.f_type as u32;
+    trace!("{} is on a filesystem of type {:#x}", target.display(), magic);
+    Some(magic)
+}
+
+/// Whether inotify sees every change made to the files of `dir`, judging
+/// by the filesystem it is on, a directory that can't be looked at is
+/// taken as reliable as there is nothing to watch there anyway
+pub fn is_reliable(dir: &Path) -> bool {
+    !filesystem_magic(dir).is_some_and(is_remote)
+}
+
+const DIR_EVENTS: u32 = libc::IN_MODIFY | libc::IN_ATTRIB | libc::IN_CREATE | libc::IN_MOVED_TO
+    | libc::IN_MOVED_FROM | libc::IN_DELETE | libc::IN_CLOSE_WRITE;
+
+/// Size of the fixed part of an event, the name follows it
+const EVENT_SIZE: usize = std::mem::size_of::<libc::inotify_event>();
+
+impl Inotify {
+    pub fn new() -> Result<Inotify, io::Error> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Inotify { fd, dirs: HashMap::new(), watches: HashMap::new(), unreliable: HashSet::new() })
+    }
+
+    /// Watches the directory a file is in, events name the file the way
+    /// it was given: the directory of `logs/a.log` is `logs`, of `a.log` it is empty.
+    /// Directories on network filesystems are refused, their files are polled.
+    pub fn watch_dir(&mut self, dir: &Path) -> Result<(), io::Error> {
+        if self.dirs.contains_key(dir) {
+            return Ok(());
+        }
+        if self.unreliable.contains(dir) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "inotify is unreliable on this filesystem"));
+        }
+        if !is_reliable(dir) {
+            info!("{} is on a network filesystem, polling its files", dir.display());
+            self.unreliable.insert(dir.to_path_buf());
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "inotify is unreliable on this filesystem"));
+        }
+        let target = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let path = CString::new(target.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), DIR_EVENTS | libc::IN_ONLYDIR) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        debug!("watching {} => {}", target.display(), wd);
+        self.dirs.insert(dir.to_path_buf(), wd);
+        self.watches.insert(wd, dir.to_path_buf());
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for something to happen, an empty list
+    /// means nothing did
+    pub fn wait(&mut self, timeout: Duration) -> Result<Vec<Event>, io::Error> {
+        let mut pollfd = libc::pollfd { fd: self.fd, events: libc::POLLIN, revents: 0 };
+        let millis = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+        let ready = unsafe { libc::poll(&mut pollfd, 1, millis) };
+        if ready < 0 {
+            let e = io::Error::last_os_error();
+            return match e.kind() {
+                io::ErrorKind::Interrupted => Ok(Vec::new()),
+                _ => Err(e),
+            };
+        }
+        let mut events = Vec::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let n = unsafe { libc::read(self.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                match e.kind() {
+                    io::ErrorKind::WouldBlock => break,
+                    io::ErrorKind::Interrupted => continue,
+                    _ => return Err(e),
+                }
+            }
+            self.parse(&buffer[..n as usize], &mut events);
+        }
+        Ok(events)
+    }
+
+    fn parse(&mut self, mut data: &[u8], events: &mut Vec<Event>) {
+        while data.len() >= EVENT_SIZE {
+            // safety: the kernel only hands back whole events, read_unaligned copes with the alignment
+            let event: libc::inotify_event = unsafe { std::ptr::read_unaligned(data.as_ptr() as *const libc::inotify_event) };
+            let end = EVENT_SIZE + event.len as usize;
+            let name = &data[EVENT_SIZE..end.min(data.len())];
+            let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+            data = &data[end.min(data.len())..];
+
+            if event.mask & libc::IN_IGNORED != 0 {
+                if let Some(dir) = self.watches.remove(&event.wd) {
+                    debug!("{} is not watched anymore", dir.display());
+                    self.dirs.remove(&dir);
+                }
+                continue;
+            }
+            if event.mask & libc::IN_Q_OVERFLOW != 0 {
+                warn!("inotify queue overflowed, the next full pass catches up");
+                continue;
+            }
+            if let Some(dir) = self.watches.get(&event.wd) {
+                events.push(Event {
+                    path: dir.join(OsStr::from_bytes(name)),
+                    created: event.mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0,
+                });
+            }
+        }
+    }
+}
+
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
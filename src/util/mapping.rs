@@ -0,0 +1,124 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::sync::Once;
+use std::sync::atomic::{compiler_fence, AtomicBool, AtomicUsize, Ordering};
+
+use memmap2::Mmap;
+
+/// Maps alive at once, files past it are read by a buffer instead
+const MAX_MAPS: usize = 4096;
+
+/// Where a map lives, the handler looks faulting addresses up here
+struct Slot {
+    /// zero when the slot is free
+    start: AtomicUsize,
+    end: AtomicUsize,
+    faulted: AtomicBool,
+}
+
+static SLOTS: [Slot; MAX_MAPS] = [const { Slot { start: AtomicUsize::new(0), end: AtomicUsize::new(0), faulted: AtomicBool::new(false) } }; MAX_MAPS];
+
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+static INSTALL: Once = Once::new();
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// A file truncated while mapped raises SIGBUS on the pages past its new
+/// end: those of one of our maps get zeros mapped over them and the map is
+/// marked faulted, any other SIGBUS kills the process as it always did
+extern "C" fn on_sigbus(_: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    let addr = unsafe { (*info).si_addr() } as usize;
+    let page = PAGE_SIZE.load(Ordering::SeqCst);
+    for slot in SLOTS.iter() {
+        let start = slot.start.load(Ordering::SeqCst);
+        let end = slot.end.load(Ordering::SeqCst);
+        if start == 0 || addr < start || addr >= end {
+            continue;
+        }
+        let from = addr & !(page - 1);
+        let zeros = unsafe {
+            libc::mmap(from as *mut libc::c_void, end - from, libc::PROT_READ,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED, -1, 0)
+        };
+        if zeros != libc::MAP_FAILED {
+            slot.faulted.store(true, Ordering::SeqCst);
+            return;
+        }
+        break;
+    }
+    // the access is retried once the handler returns, and gets the default action
+    unsafe { libc::signal(libc::SIGBUS, libc::SIG_DFL) };
+}
+
+fn install() -> bool {
+    INSTALL.call_once(|| {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        if page <= 0 {
+            debug!("not able to get the page size => {}", io::Error::last_os_error());
+            return;
+        }
+        PAGE_SIZE.store(page as usize, Ordering::SeqCst);
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = on_sigbus as *const () as usize;
+        action.sa_flags = libc::SA_SIGINFO;
+        unsafe { libc::sigemptyset(&mut action.sa_mask) };
+        if unsafe { libc::sigaction(libc::SIGBUS, &action, std::ptr::null_mut()) } != 0 {
+            debug!("not able to handle SIGBUS => {}", io::Error::last_os_error());
+            return;
+        }
+        INSTALLED.store(true, Ordering::SeqCst);
+    });
+    INSTALLED.load(Ordering::SeqCst)
+}
+
+/// A read only map of a file that survives the file being truncated under
+/// it: what is past the new end reads as zeros and is_faulted tells so
+pub struct Map {
+    map: Mmap,
+    slot: usize,
+}
+
+/// Maps `file`, an error when no map can be guarded against truncation
+pub fn map(file: &File) -> Result<Map, io::Error> {
+    if !install() {
+        return Err(io::Error::other("SIGBUS can't be handled"));
+    }
+    // safety: the file may shrink while mapped, touching the pages past its
+    // new end raises SIGBUS, which on_sigbus turns into zeros once the map
+    // is in its slot, readers check is_faulted before trusting what they read
+    let map = unsafe { Mmap::map(file)? };
+    let start = map.as_ptr() as usize;
+    let slot = SLOTS.iter()
+        .position(|s| s.start.compare_exchange(0, start, Ordering::SeqCst, Ordering::SeqCst).is_ok())
+        .ok_or_else(|| io::Error::other("too many files mapped"))?;
+    SLOTS[slot].faulted.store(false, Ordering::SeqCst);
+    SLOTS[slot].end.store(start + map.len(), Ordering::SeqCst);
+    Ok(Map { map, slot })
+}
+
+impl Map {
+    /// Whether part of the map was found past the end of the file, what
+    /// was read from it since is not what the file holds
+    pub fn is_faulted(&self) -> bool {
+        // the handler runs on this thread, the reads before must not move past the check
+        compiler_fence(Ordering::SeqCst);
+        SLOTS[self.slot].faulted.load(Ordering::SeqCst)
+    }
+}
+
+impl Deref for Map {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl Drop for Map {
+    fn drop(&mut self) {
+        let slot = &SLOTS[self.slot];
+        slot.end.store(0, Ordering::SeqCst);
+        slot.start.store(0, Ordering::SeqCst);
+    }
+}
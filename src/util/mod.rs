@@ -0,0 +1,16 @@
+
+pub mod checkpoint;
+pub mod compression;
+pub mod discovery;
+pub mod error;
+pub mod file;
+pub mod file_read_strategy;
+pub mod inotify;
+pub mod mapping;
+pub mod number;
+pub mod obsolete;
+pub mod output;
+pub mod reverse;
+pub mod size;
+pub mod stdin;
+pub mod timestamp;
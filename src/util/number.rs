@@ -0,0 +1,59 @@
+/// Where a line number sits in its column
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+    /// right aligned, the column filled with leading zeros
+    RightZeros,
+}
+
+impl Align {
+    /// nl's -n FORMAT: ln, rn or rz
+    pub fn parse(format: &str) -> Option<Align> {
+        match format {
+            "ln" => Some(Align::Left),
+            "rn" => Some(Align::Right),
+            "rz" => Some(Align::RightZeros),
+            _ => None,
+        }
+    }
+}
+
+/// How line numbers are laid out before the lines, shared by nl and
+/// cat -n so both write the same columns
+#[derive(Debug, Clone)]
+pub struct Numbering {
+    pub align: Align,
+    /// numbers are at least this wide, never cut when longer
+    pub width: usize,
+    /// what comes between the number and the line
+    pub separator: Vec<u8>,
+}
+
+impl Default for Numbering {
+    /// Six columns right aligned and a tab, gnu cat's "%6d\t" and nl's defaults
+    fn default() -> Numbering {
+        Numbering { align: Align::Right, width: 6, separator: b"\t".to_vec() }
+    }
+}
+
+impl Numbering {
+    /// `number` in its column followed by the separator
+    pub fn number(&self, number: i64) -> Vec<u8> {
+        let width = self.width;
+        let column = match self.align {
+            Align::Left => format!("{:<width$}", number),
+            Align::Right => format!("{:>width$}", number),
+            Align::RightZeros => format!("{:0width$}", number),
+        };
+        let mut prefix = column.into_bytes();
+        prefix.extend_from_slice(&self.separator);
+        prefix
+    }
+
+    /// What lines left unnumbered start with, blanks as wide as
+    /// a number and its separator
+    pub fn blank(&self) -> Vec<u8> {
+        vec![b' '; self.width + self.separator.len()]
+    }
+}
@@ -0,0 +1,112 @@
+use std::env;
+use std::ffi::OsString;
+
+/// Count taken when an obsolete option gives none, as in tail -f or tail -c
+const DEFAULT_COUNT: u64 = 10;
+
+/// Rewrites head's traditional -NUM[bkm][cl][qv] first argument into -n, -c,
+/// -q and -v, any other letter after the digits is an invalid trailing option
+pub fn head(args: Vec<OsString>) -> Result<Vec<OsString>, String> {
+    let option = match first_option(&args) {
+        Some(o) if o.starts_with('-') && o[1..].starts_with(|c: char| c.is_ascii_digit()) => o[1..].to_string(),
+        _ => return Ok(args),
+    };
+    let digits = digits_end(&option);
+    let mut bytes = false;
+    let mut multiplier = None;
+    let mut rewritten = vec![];
+    for c in option[digits..].chars() {
+        match c {
+            'c' => {
+                bytes = true;
+                multiplier = None;
+            },
+            'b' | 'k' | 'm' => {
+                bytes = true;
+                multiplier = Some(c);
+            },
+            'l' => bytes = false,
+            'q' => rewritten.push("-q".to_string()),
+            'v' => rewritten.push("-v".to_string()),
+            _ => return Err(format!("invalid trailing option -- {}", c)),
+        }
+    }
+    let mut count = option[..digits].to_string();
+    count.extend(multiplier);
+    rewritten.insert(0, count);
+    rewritten.insert(0, if bytes { "-c" } else { "-n" }.to_string());
+    Ok(splice(args, rewritten))
+}
+
+/// Rewrites tail's traditional [-+]NUM[bcl][f|r] into -n, -c and -f or -r,
+/// the r of bsd tail reversing the lines. It is only
+/// taken as one when followed by a single file at most, +NUM is a file name
+/// when _POSIX2_VERSION asks for POSIX 1003.1-2001, the one standard without
+/// it, and a lone - or -c keep their modern meaning
+pub fn tail(args: Vec<OsString>) -> Vec<OsString> {
+    let rest = &args[args.len().min(2)..];
+    let single_file = match rest {
+        [] => true,
+        [file] => file == "-" || file == "--" || !file.to_string_lossy().starts_with('-'),
+        [dashes, _] => dashes == "--",
+        _ => false,
+    };
+    let option = match first_option(&args) {
+        Some(o) if single_file => o.to_string(),
+        _ => return args,
+    };
+    let (from_start, spec) = match option.split_at(option.len().min(1)) {
+        ("+", spec) if !(200112..200809).contains(&posix2_version()) => (true, spec),
+        ("-", spec) if !spec.is_empty() && spec != "c" => (false, spec),
+        _ => return args,
+    };
+    let digits = digits_end(spec);
+    let mut count = match &spec[..digits] {
+        "" => Some(DEFAULT_COUNT),
+        d => d.parse::<u64>().ok(),
+    };
+    let mut modifiers = &spec[digits..];
+    let bytes = modifiers.starts_with(['b', 'c']);
+    if modifiers.starts_with('b') {
+        count = count.and_then(|c| c.checked_mul(512));
+    }
+    if modifiers.starts_with(['b', 'c', 'l']) {
+        modifiers = &modifiers[1..];
+    }
+    let follow_or_reverse = match modifiers {
+        "" => None,
+        "f" => Some("-f"),
+        "r" => Some("-r"),
+        _ => return args,
+    };
+    // a count too large is left for the modern option to complain about
+    let count = count.map_or_else(|| spec[..digits].to_string(), |c| c.to_string());
+    let mut rewritten = vec![
+        if bytes { "-c" } else { "-n" }.to_string(),
+        if from_start { format!("+{}", count) } else { count },
+    ];
+    rewritten.extend(follow_or_reverse.map(String::from));
+    splice(args, rewritten)
+}
+
+/// The POSIX version in _POSIX2_VERSION, 200809 when unset or not a number
+fn posix2_version() -> u64 {
+    env::var("_POSIX2_VERSION").ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(200809)
+}
+
+/// The first argument after the program name, when it is valid unicode
+fn first_option(args: &[OsString]) -> Option<&str> {
+    args.get(1)?.to_str()
+}
+
+fn digits_end(text: &str) -> usize {
+    text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len())
+}
+
+/// Puts `rewritten` where the obsolete option was
+fn splice(mut args: Vec<OsString>, rewritten: Vec<String>) -> Vec<OsString> {
+    args.splice(1..2, rewritten.into_iter().map(OsString::from));
+    args
+}
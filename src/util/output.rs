@@ -0,0 +1,60 @@
+use std::io::{self, BufWriter, Write};
+
+/// Size of the buffer output is held in when block buffered
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// When what is written reaches stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Buffering {
+    /// once a line is complete, the default on terminals
+    Line,
+    /// once a block is full, the default on files and pipes
+    Block,
+    /// right away
+    Unbuffered,
+}
+
+impl Buffering {
+    /// The buffering asked for, otherwise lines on terminals and blocks elsewhere
+    pub fn pick(line_buffered: bool, unbuffered: bool) -> Buffering {
+        if unbuffered {
+            return Buffering::Unbuffered;
+        }
+        if line_buffered || is_terminal() {
+            return Buffering::Line;
+        }
+        Buffering::Block
+    }
+}
+
+pub fn is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/// Stdout buffered as picked, it has to be flushed before exiting
+/// as process::exit doesn't run destructors
+pub struct Output {
+    writer: BufWriter<io::Stdout>,
+    buffering: Buffering,
+}
+
+pub fn stdout(buffering: Buffering) -> Output {
+    debug!("stdout buffering => {:?}", buffering);
+    Output { writer: BufWriter::with_capacity(BLOCK_SIZE, io::stdout()), buffering }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.writer.write(buf)?;
+        match self.buffering {
+            Buffering::Unbuffered => self.writer.flush()?,
+            Buffering::Line if memchr::memchr(b'\n', &buf[..n]).is_some() => self.writer.flush()?,
+            _ => {},
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,157 @@
+use std::io::{self, Write};
+
+use memchr::memmem;
+use regex::bytes::Regex;
+
+use super::file::FileDetail;
+
+/// Bytes read at once going backwards
+const BLOCK: usize = 64 * 1024;
+
+/// Longest stretch a regex separator is looked for across two blocks
+const REGEX_OVERLAP: usize = BLOCK;
+
+/// Bytes searched first for a regex separator, from the end of the window
+const REGEX_TAIL: usize = 32;
+
+/// What records are told apart by
+pub enum Separator {
+    Text(Vec<u8>),
+    Regex {
+        any: Regex,
+        /// the same one only matching where the searched bytes start
+        anchored: Regex,
+    },
+}
+
+impl Separator {
+    pub fn text(separator: &str) -> Separator {
+        Separator::Text(separator.as_bytes().to_vec())
+    }
+
+    pub fn regex(pattern: &str) -> Result<Separator, regex::Error> {
+        Ok(Separator::Regex {
+            any: Regex::new(pattern)?,
+            anchored: Regex::new(&format!("^(?:{})", pattern))?,
+        })
+    }
+
+    /// The match starting last in `data`, as found searching backwards the way gnu tac
+    /// does: "22" is two separators for [0-9]+. Empty separators never match.
+    fn rfind(&self, data: &[u8]) -> Option<(usize, usize)> {
+        match self {
+            Separator::Text(t) if t.is_empty() => None,
+            Separator::Text(t) => memmem::rfind(data, t).map(|s| (s, s + t.len())),
+            Separator::Regex { any, anchored } => {
+                // the end of data is looked at first, twice as much each time
+                // nothing is found, not to search it all again for every record
+                let mut from = data.len().saturating_sub(REGEX_TAIL);
+                loop {
+                    if let Some(found) = rfind_regex(any, anchored, &data[from..]) {
+                        return Some((from + found.0, from + found.1));
+                    }
+                    if from == 0 {
+                        return None;
+                    }
+                    from = from.saturating_sub(data.len() - from);
+                }
+            },
+        }
+    }
+
+    /// How much of a block has to be searched again along with the block
+    /// before it, for separators spanning both
+    fn overlap(&self) -> usize {
+        match self {
+            Separator::Text(t) => t.len().saturating_sub(1),
+            Separator::Regex { .. } => REGEX_OVERLAP,
+        }
+    }
+}
+
+/// The match of `any` starting last in `data`, the next match can't
+/// start past the end of the last one found going forward
+fn rfind_regex(any: &Regex, anchored: &Regex, data: &[u8]) -> Option<(usize, usize)> {
+    let last = any.find_iter(data).filter(|m| m.start() < m.end()).last()?;
+    let inner = (last.start() + 1..last.end()).rev().find_map(|s| {
+        anchored.find(&data[s..]).filter(|m| m.end() > 0).map(|m| (s, s + m.end()))
+    });
+    Some(inner.unwrap_or((last.start(), last.end())))
+}
+
+/// Writes records in reverse order, each one keeping its separator
+/// after it, or before it with `before` as tac -b does
+pub struct Reverse {
+    pub separator: Separator,
+    pub before: bool,
+}
+
+impl Reverse {
+    /// Writes the records of `file` between offsets `start` and `end` last
+    /// first, `records` of them at most. The file is read backwards a block
+    /// at a time, records longer than a block are copied from the file
+    /// rather than held in memory. `file` has to be readable at an offset,
+    /// see FileDetail::spill.
+    pub fn write(&self, file: &FileDetail, start: u64, end: u64, records: Option<u64>, out: &mut dyn Write) -> io::Result<()> {
+        let mut written = 0;
+        // holds the file from window_start on
+        let mut window: Vec<u8> = Vec::new();
+        let mut window_start = end;
+        // separators are looked for before limit, the record being looked for ends at record_end
+        let mut limit = end;
+        let mut record_end = end;
+        while records.is_none_or(|r| written < r) {
+            let searched = &window[..(limit - window_start) as usize];
+            match self.separator.rfind(searched) {
+                Some((s, e)) => {
+                    let (s, e) = (window_start + s as u64, window_start + e as u64);
+                    let cut = if self.before { s } else { e };
+                    if cut < record_end {
+                        copy(file, &window, window_start, cut, record_end, out)?;
+                        written += 1;
+                    }
+                    record_end = cut;
+                    limit = s;
+                },
+                None if window_start == start => {
+                    if start < record_end {
+                        copy(file, &window, window_start, start, record_end, out)?;
+                    }
+                    break;
+                },
+                None => {
+                    let n = (BLOCK as u64).min(window_start - start);
+                    let mut block = read(file, window_start - n, n as usize)?;
+                    let kept = (self.separator.overlap() as u64).min(limit - window_start) as usize;
+                    block.extend_from_slice(&window[..kept]);
+                    window = block;
+                    window_start -= n;
+                    limit = window_start + n + kept as u64;
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the file from `from` to `to`, out of `window` when it holds them
+fn copy(file: &FileDetail, window: &[u8], window_start: u64, from: u64, to: u64, out: &mut dyn Write) -> io::Result<()> {
+    if from >= window_start && to <= window_start + window.len() as u64 {
+        return out.write_all(&window[(from - window_start) as usize..(to - window_start) as usize]);
+    }
+    let mut at = from;
+    while at < to {
+        let n = (BLOCK as u64).min(to - at);
+        out.write_all(&read(file, at, n as usize)?)?;
+        at += n;
+    }
+    Ok(())
+}
+
+fn read(file: &FileDetail, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    match file.read_at(offset, len) {
+        Some(b) if b.len() == len => Ok(b),
+        Some(_) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file truncated")),
+        None => Err(io::Error::other("can't be read at an offset")),
+    }
+}
@@ -0,0 +1,51 @@
+/// Why a count given on the command line was refused
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SizeError {
+    Invalid,
+    TooLarge,
+}
+
+impl SizeError {
+    /// The message gnu adds after the argument, if any
+    pub fn reason(self) -> Option<&'static str> {
+        match self {
+            SizeError::Invalid => None,
+            SizeError::TooLarge => Some("Value too large for defined data type"),
+        }
+    }
+}
+
+/// Multipliers in the order of their powers, K is 1024 or 1000, M its square...
+const POWERS: &str = "KMGTPEZYRQ";
+
+/// A count with an optional multiplier as gnu takes them: b for 512,
+/// K, M, G, T, P, E, Z, Y, R and Q or KiB, MiB... for powers of 1024,
+/// KB, MB... for powers of 1000, k and m are taken for K and M
+pub fn parse(arg: &str) -> Result<u64, SizeError> {
+    let digits = arg.find(|c: char| !c.is_ascii_digit()).unwrap_or(arg.len());
+    if digits == 0 {
+        return Err(SizeError::Invalid);
+    }
+    let multiplier = multiplier(&arg[digits..]).ok_or(SizeError::Invalid)?;
+    let count: u64 = arg[..digits].parse().map_err(|_| SizeError::TooLarge)?;
+    multiplier.and_then(|m| count.checked_mul(m)).ok_or(SizeError::TooLarge)
+}
+
+/// None when the suffix isn't one, Some(None) when it overflows
+fn multiplier(suffix: &str) -> Option<Option<u64>> {
+    let mut chars = suffix.chars();
+    let unit = match chars.next() {
+        None => return Some(Some(1)),
+        Some('b') if suffix.len() == 1 => return Some(Some(512)),
+        Some('k') => 'K',
+        Some('m') => 'M',
+        Some(c) => c,
+    };
+    let power = POWERS.find(unit)? as u32 + 1;
+    let base: u64 = match chars.as_str() {
+        "" | "iB" => 1024,
+        "B" => 1000,
+        _ => return None,
+    };
+    Some(base.checked_pow(power))
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::io;
+
+/// What standard input is connected to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// an anonymous pipe, as in `cmd | tail`
+    Pipe,
+    /// a named pipe, it may get new writers once the current ones are gone
+    Fifo,
+    /// a regular file redirected in, it can seek and grow
+    File,
+    Terminal,
+    Other,
+}
+
+pub fn kind() -> Kind {
+    let mut st: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(libc::STDIN_FILENO, &mut st) } != 0 {
+        debug!("not able to fstat stdin => {}", io::Error::last_os_error());
+        return Kind::Other;
+    }
+    match st.st_mode & libc::S_IFMT {
+        libc::S_IFREG => Kind::File,
+        // anonymous pipes have no name, /proc shows them as pipe:[inode]
+        libc::S_IFIFO => match fs::read_link("/proc/self/fd/0") {
+            Ok(target) if target.to_string_lossy().starts_with("pipe:") => Kind::Pipe,
+            _ => Kind::Fifo,
+        },
+        _ if unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 => Kind::Terminal,
+        _ => Kind::Other,
+    }
+}
+
+/// Reads from stdin stop waiting for writers, nothing to read is then
+/// an error instead of blocking everything else
+pub fn set_nonblocking() -> Result<(), io::Error> {
+    let flags = unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(libc::STDIN_FILENO, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
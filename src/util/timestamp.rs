@@ -0,0 +1,171 @@
+use chrono::format::{self, Parsed, StrftimeItems};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+/// Stretch at the start of a line looked at for a timestamp, common log
+/// format lines have theirs after the client address
+const HEAD: usize = 256;
+
+/// Date and time layouts without a time zone, taken as local time
+const NAIVE_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+];
+
+/// Where a line stands in the window of --since and --until
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Place {
+    Before,
+    Within,
+    After,
+}
+
+/// The lines to print, from since on and through until
+#[derive(Debug)]
+pub struct Window {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// relative times and syslog timestamps missing their year are taken from it
+    now: DateTime<Local>,
+}
+
+impl Window {
+    pub fn new(since: Option<&str>, until: Option<&str>, now: DateTime<Local>) -> Result<Window, String> {
+        let bound = |arg: Option<&str>| arg.map(|a| parse_time(a, now).ok_or_else(|| format!("invalid time: '{}'", a))).transpose();
+        Ok(Window { since: bound(since)?, until: bound(until)?, now })
+    }
+
+    /// None for lines without a timestamp
+    pub fn place(&self, line: &[u8]) -> Option<Place> {
+        let time = parse_line(line, self.now)?;
+        if self.since.is_some_and(|since| time < since) {
+            return Some(Place::Before);
+        }
+        if self.until.is_some_and(|until| time > until) {
+            return Some(Place::After);
+        }
+        Some(Place::Within)
+    }
+}
+
+/// The timestamp a log line starts with: RFC 3339 and alike, syslog,
+/// epoch seconds or milliseconds, or the bracketed one of common log format
+pub fn parse_line(line: &[u8], now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let head = &line[..line.len().min(HEAD)];
+    let text = match std::str::from_utf8(head) {
+        Ok(t) => t,
+        Err(e) => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+    };
+    let text = text.trim_start();
+    iso(text).or_else(|| syslog(text, now)).or_else(|| epoch(text)).or_else(|| clf(text)).map(|(time, _)| time)
+}
+
+/// A --since or --until argument: any timestamp a line may start with,
+/// a date, "now" or a duration back from now such as 10m, 2h or "3 days ago"
+pub fn parse_time(arg: &str, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let arg = arg.trim();
+    if arg == "now" {
+        return Some(now.with_timezone(&Utc));
+    }
+    if let Some(back) = duration(arg) {
+        return Some((now - back).with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(arg, "%Y-%m-%d") {
+        return local(date.and_hms_opt(0, 0, 0)?);
+    }
+    let epoch_arg = arg.strip_prefix('@').unwrap_or(arg);
+    let (time, rest) = iso(arg).or_else(|| syslog(arg, now)).or_else(|| epoch(epoch_arg)).or_else(|| clf(arg))?;
+    if rest.trim().is_empty() { Some(time) } else { None }
+}
+
+/// 10m, 2h, "90 seconds" or "3 days ago"
+fn duration(arg: &str) -> Option<Duration> {
+    let arg = arg.strip_suffix("ago").unwrap_or(arg).trim_end();
+    let digits = arg.find(|c: char| !c.is_ascii_digit())?;
+    let count: i64 = arg[..digits].parse().ok()?;
+    let seconds = match arg[digits..].trim_start() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86400,
+        "w" | "week" | "weeks" => 7 * 86400,
+        _ => return None,
+    };
+    Duration::try_seconds(count.checked_mul(seconds)?)
+}
+
+fn local(time: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// 2024-01-02T03:04:05.678+01:00, with or without zone, T or a space
+fn iso(text: &str) -> Option<(DateTime<Utc>, &str)> {
+    let bytes = text.as_bytes();
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    if let Ok(time) = DateTime::parse_from_rfc3339(&text[..end]) {
+        return Some((time.with_timezone(&Utc), &text[end..]));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f%:z", "%Y-%m-%d %H:%M:%S%.f %z"] {
+        if let Ok((time, rest)) = DateTime::parse_and_remainder(text, format) {
+            return Some((time.with_timezone(&Utc), rest));
+        }
+    }
+    NAIVE_FORMATS.iter().find_map(|format| {
+        let (time, rest) = NaiveDateTime::parse_and_remainder(text, format).ok()?;
+        Some((local(time)?, rest))
+    })
+}
+
+/// Jan  2 03:04:05, in the last year up to a day ahead of now
+fn syslog(text: &str, now: DateTime<Local>) -> Option<(DateTime<Utc>, &str)> {
+    let mut parsed = Parsed::new();
+    let rest = format::parse_and_remainder(&mut parsed, text, StrftimeItems::new("%b %e %H:%M:%S")).ok()?;
+    let in_year = |year: i32| {
+        let mut parsed = parsed.clone();
+        parsed.set_year(year as i64).ok()?;
+        local(parsed.to_naive_datetime_with_offset(0).ok()?)
+    };
+    let time = in_year(now.year())?;
+    if time > now.with_timezone(&Utc) + Duration::days(1) {
+        return Some((in_year(now.year() - 1)?, rest));
+    }
+    Some((time, rest))
+}
+
+/// Seconds since the epoch, with an optional fraction, or milliseconds
+fn epoch(text: &str) -> Option<(DateTime<Utc>, &str)> {
+    let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let rest = &text[digits..];
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '-' || c == ':') {
+        return None;
+    }
+    let value: i64 = text[..digits].parse().ok()?;
+    match digits {
+        10 => {
+            let fraction = rest.strip_prefix('.').map(|f| &f[..f.find(|c: char| !c.is_ascii_digit()).unwrap_or(f.len())]);
+            let nanos = match fraction {
+                Some(f) if !f.is_empty() => format!("{:0<9}", &f[..f.len().min(9)]).parse().ok()?,
+                _ => 0,
+            };
+            let skipped = fraction.map_or(0, |f| f.len() + 1);
+            Some((Utc.timestamp_opt(value, nanos).single()?, &rest[skipped..]))
+        },
+        13 => Some((Utc.timestamp_millis_opt(value).single()?, rest)),
+        _ => None,
+    }
+}
+
+/// 127.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "GET / HTTP/1.0" 200 2326
+fn clf(text: &str) -> Option<(DateTime<Utc>, &str)> {
+    let start = text.find('[')? + 1;
+    // host, ident and user come first, a bracketed date further on belongs to the message
+    if text[..start - 1].split_whitespace().count() != 3 {
+        return None;
+    }
+    let (time, rest) = DateTime::parse_and_remainder(&text[start..], "%d/%b/%Y:%H:%M:%S %z").ok()?;
+    Some((time.with_timezone(&Utc), rest.strip_prefix(']').unwrap_or(rest)))
+}
@@ -0,0 +1,98 @@
+mod common;
+
+gnu_case!(single_file, "cat", ["lines.txt"]);
+gnu_case!(many_files, "cat", ["short.txt", "lines.txt", "short.txt"]);
+gnu_case!(empty_file, "cat", ["empty.txt"]);
+gnu_case!(no_trailing_new_line, "cat", ["no_newline.txt"]);
+gnu_case!(from_stdin, "cat", [], stdin = "short.txt");
+gnu_case!(dash_is_stdin, "cat", ["short.txt", "-"], stdin = "lines.txt");
+gnu_case!(missing_file, "cat", ["missing.txt"]);
+gnu_case!(missing_file_among_others, "cat", ["short.txt", "missing.txt", "short.txt"]);
+gnu_case!(directory, "cat", ["dir"]);
+gnu_case!(show_ends, "cat", ["-E", "blanks.txt"]);
+gnu_case!(show_tabs, "cat", ["-T", "blanks.txt"]);
+gnu_case!(squeeze_blank, "cat", ["-s", "blanks.txt"]);
+gnu_case!(number, "cat", ["-n", "blanks.txt"]);
+gnu_case!(number_nonblank, "cat", ["-b", "blanks.txt"]);
+gnu_case!(binary_file, "cat", ["binary.bin"]);
+gnu_case!(unbuffered, "cat", ["-u", "lines.txt"], golden = single_file);
+gnu_case!(decompress_gzip, "cat", ["--decompress", "lines.txt.gz"], golden = single_file);
+gnu_case!(decompress_zstd, "cat", ["--decompress", "lines.txt.zst"], golden = single_file);
+gnu_case!(decompress_xz, "cat", ["--decompress", "lines.txt.xz"], golden = single_file);
+gnu_case!(decompress_plain_file, "cat", ["--decompress", "lines.txt"], golden = single_file);
+gnu_case!(decompress_stdin, "cat", ["--decompress"], stdin = "short.txt", golden = from_stdin);
+
+#[test]
+fn large_file_is_mapped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 20_000);
+    let output = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &[path.to_str().unwrap()], None);
+    assert_eq!(output.stdout, std::fs::read(&path).unwrap());
+}
+
+#[test]
+fn procfs_files_are_buffered() {
+    let output = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &["/proc/self/status"], None);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Name:\tcat\n"));
+}
+
+#[test]
+fn lines_from_a_slow_pipe() {
+    let pieces: &[&[u8]] = &[b"fir", b"st\nsec", b"ond\n", b"\n", b"last"];
+    let output = common::run_slow(env!("CARGO_BIN_EXE_cat"), &[], pieces);
+    assert_eq!(output.stdout, b"first\nsecond\n\nlast");
+}
+
+#[test]
+fn unbuffered_lines_show_up_before_stdin_closes() {
+    use std::io::Write;
+    let dir = tempfile::tempdir().unwrap();
+    let (reader, mut writer) = std::io::pipe().unwrap();
+    let cat = common::Running::start_with_stdin(env!("CARGO_BIN_EXE_cat"), &["-u"], dir.path(), reader.into());
+    writer.write_all(b"first\n").unwrap();
+    assert!(cat.wait_for("first\n"), "got {:?}", cat.stdout());
+}
+
+#[test]
+fn block_buffered_output_waits_for_stdin_to_close() {
+    use std::io::Write;
+    let dir = tempfile::tempdir().unwrap();
+    let (reader, mut writer) = std::io::pipe().unwrap();
+    let cat = common::Running::start_with_stdin(env!("CARGO_BIN_EXE_cat"), &[], dir.path(), reader.into());
+    writer.write_all(b"first\n").unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert_eq!(cat.stdout(), "");
+    drop(writer);
+    assert!(cat.wait_for("first\n"), "got {:?}", cat.stdout());
+}
+
+#[test]
+fn binary_files_are_copied_whole() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mixed.bin");
+    let mut content = b"text\n\xff\xfe not text\n".to_vec();
+    content.extend((1..=20_000).flat_map(|n| format!("line {}\n", n).into_bytes()));
+    std::fs::write(&path, &content).unwrap();
+    let path = path.to_str().unwrap();
+
+    let output = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &[path], None);
+    assert_eq!(output.stdout, content);
+
+    let output = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &["-E", path], None);
+    let ends: Vec<u8> = content.iter().flat_map(|&b| if b == b'\n' { vec![b'$', b'\n'] } else { vec![b] }).collect();
+    assert_eq!(output.stdout, ends);
+}
+
+#[test]
+fn damaged_gzip_input_is_an_error() {
+    let lines = std::fs::read(common::fixtures().join("lines.txt")).unwrap();
+    let output = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &["--decompress", "truncated.gz"], None);
+    assert!(!output.stdout.is_empty() && lines.starts_with(&output.stdout), "got {:?}", output.stdout);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "cat: truncated.gz: incomplete deflate stream\n");
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &["--decompress", "corrupt.gz"], None);
+    assert_eq!(output.stdout, lines);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "cat: corrupt.gz: corrupt gzip stream does not have a matching checksum\n");
+    assert_eq!(output.status.code(), Some(1));
+}
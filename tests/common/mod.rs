@@ -0,0 +1,241 @@
+// shared by every integration test binary, each one uses part of it
+#![allow(dead_code)]
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+
+/// Directory holding the input files every case runs against,
+/// it is also the working directory of the command under test.
+pub fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+}
+
+fn golden(bin: &str, case: &str, ext: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(bin)
+        .join(format!("{}.{}", case, ext))
+}
+
+/// Runs `program` as `bin` from the fixtures directory, feeding it the
+/// fixture `stdin` when given, under the C locale so messages are stable.
+pub fn run(program: &str, bin: &str, args: &[&str], stdin: Option<&str>) -> Output {
+    let input = match stdin {
+        Some(name) => Stdio::from(File::open(fixtures().join(name)).unwrap()),
+        None => Stdio::null(),
+    };
+    let mut cmd = Command::new(program);
+    #[cfg(unix)]
+    cmd.arg0(bin);
+    cmd.args(args)
+        .current_dir(fixtures())
+        .env("LC_ALL", "C")
+        .env_remove("POSIXLY_CORRECT")
+        .env_remove("RUST_LOG")
+        .stdin(input)
+        .output()
+        .unwrap_or_else(|e| panic!("failed to run {}: {}", program, e))
+}
+
+/// Runs our build of `bin` and compares stdout, stderr and the exit code
+/// with the golden files `golden_case` captured from gnu-coreutils.
+///
+/// With GOLDEN_CAPTURE set the gnu tool found on PATH is run instead and
+/// its output becomes the new golden files, cases borrowing the golden
+/// files of another case are skipped as gnu doesn't know their options.
+pub fn check(bin: &str, program: &str, case: &str, golden_case: &str, args: &[&str], stdin: Option<&str>) {
+    if env::var_os("GOLDEN_CAPTURE").is_some() {
+        if case != golden_case {
+            return;
+        }
+        let output = run(bin, bin, args, stdin);
+        fs::create_dir_all(golden(bin, case, "out").parent().unwrap()).unwrap();
+        fs::write(golden(bin, case, "out"), &output.stdout).unwrap();
+        fs::write(golden(bin, case, "err"), &output.stderr).unwrap();
+        fs::write(golden(bin, case, "code"), format!("{}\n", output.status.code().unwrap_or(-1))).unwrap();
+        return;
+    }
+
+    let read = |ext| {
+        let path = golden(bin, golden_case, ext);
+        fs::read(&path).unwrap_or_else(|e| panic!("missing golden file {}: {}", path.display(), e))
+    };
+    let output = run(program, bin, args, stdin);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&read("out")),
+        "{} {:?}: stdout differs", bin, args
+    );
+    assert_eq!(output.stdout, read("out"), "{} {:?}: stdout bytes differ", bin, args);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        String::from_utf8_lossy(&read("err")),
+        "{} {:?}: stderr differs", bin, args
+    );
+    let code = String::from_utf8(read("code")).unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(code.trim().parse().unwrap()),
+        "{} {:?}: exit code differs", bin, args
+    );
+}
+
+/// Declares a test comparing one invocation against its golden files.
+///
+///     gnu_case!(lines_default, "head", ["lines.txt"]);
+///     gnu_case!(from_stdin, "head", ["-n", "2"], stdin = "lines.txt");
+///     gnu_case!(gzip, "head", ["--decompress", "lines.txt.gz"], golden = lines_default);
+#[macro_export]
+macro_rules! gnu_case {
+    ($(#[$meta:meta])* $case:ident, $bin:tt, [$($arg:expr),*]) => {
+        gnu_case!($(#[$meta])* $case, $bin, [$($arg),*], golden = $case);
+    };
+    ($(#[$meta:meta])* $case:ident, $bin:tt, [$($arg:expr),*], golden = $golden:ident) => {
+        $(#[$meta])*
+        #[test]
+        fn $case() {
+            common::check($bin, env!(concat!("CARGO_BIN_EXE_", $bin)), stringify!($case), stringify!($golden), &[$($arg),*], None);
+        }
+    };
+    ($(#[$meta:meta])* $case:ident, $bin:tt, [$($arg:expr),*], stdin = $stdin:expr) => {
+        gnu_case!($(#[$meta])* $case, $bin, [$($arg),*], stdin = $stdin, golden = $case);
+    };
+    ($(#[$meta:meta])* $case:ident, $bin:tt, [$($arg:expr),*], stdin = $stdin:expr, golden = $golden:ident) => {
+        $(#[$meta])*
+        #[test]
+        fn $case() {
+            common::check($bin, env!(concat!("CARGO_BIN_EXE_", $bin)), stringify!($case), stringify!($golden), &[$($arg),*], Some($stdin));
+        }
+    };
+}
+
+/// Writes `lines` numbered lines into `dir`, big enough to be memory mapped
+pub fn large_file(dir: &Path, name: &str, lines: usize) -> PathBuf {
+    let path = dir.join(name);
+    let content: String = (1..=lines).map(|n| format!("line {}\n", n)).collect();
+    fs::write(&path, content).unwrap();
+    path
+}
+
+/// A command left running in the background, as tail -f, killed on drop
+pub struct Running {
+    child: Child,
+    stdout: Arc<Mutex<Vec<u8>>>,
+    stderr: Arc<Mutex<Vec<u8>>>,
+}
+
+/// Collects whatever `pipe` gives until it is closed
+fn collect<R: Read + Send + 'static>(mut pipe: R) -> Arc<Mutex<Vec<u8>>> {
+    let collected = Arc::new(Mutex::new(Vec::new()));
+    let sink = collected.clone();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut buffer) {
+            if n == 0 {
+                break;
+            }
+            sink.lock().unwrap().extend_from_slice(&buffer[..n]);
+        }
+    });
+    collected
+}
+
+impl Running {
+    pub fn start(program: &str, args: &[&str], dir: &Path) -> Running {
+        Running::start_with_stdin(program, args, dir, Stdio::null())
+    }
+
+    pub fn start_with_stdin(program: &str, args: &[&str], dir: &Path, stdin: Stdio) -> Running {
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .env("LC_ALL", "C")
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|e| panic!("failed to run {}: {}", program, e));
+        let stdout = collect(child.stdout.take().unwrap());
+        let stderr = collect(child.stderr.take().unwrap());
+        Running { child, stdout, stderr }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.stdout.lock().unwrap()).to_string()
+    }
+
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.lock().unwrap()).to_string()
+    }
+
+    /// Waits up to five seconds for `needle` to show up on stdout
+    pub fn wait_for(&self, needle: &str) -> bool {
+        wait_until(|| self.stdout().contains(needle))
+    }
+
+    /// Waits up to five seconds for `needle` to show up on stderr
+    pub fn wait_for_error(&self, needle: &str) -> bool {
+        wait_until(|| self.stderr().contains(needle))
+    }
+}
+
+fn wait_until(done: impl Fn() -> bool) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if done() {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Appends `content` to the file at `path`
+pub fn append(path: &Path, content: &str) {
+    let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+}
+
+/// Runs `program` feeding its stdin from a pipe that delivers `pieces`
+/// one at a time with a pause in between, the way slow producers do
+pub fn run_slow(program: &str, args: &[&str], pieces: &[&[u8]]) -> Output {
+    let mut child = Command::new(program)
+        .args(args)
+        .env("LC_ALL", "C")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|e| panic!("failed to run {}: {}", program, e));
+    let mut stdin = child.stdin.take().unwrap();
+    for piece in pieces {
+        // the command may be done before every piece is sent, as head is
+        if stdin.write_all(piece).and_then(|_| stdin.flush()).is_err() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(30));
+    }
+    drop(stdin);
+    child.wait_with_output().unwrap()
+}
@@ -0,0 +1,12 @@
+mod common;
+
+gnu_case!(words, "echo", ["hello", "world"]);
+gnu_case!(no_arguments, "echo", []);
+gnu_case!(no_new_line, "echo", ["-n", "hello"]);
+gnu_case!(escapes_disabled_by_default, "echo", ["tab\\there"]);
+gnu_case!(escapes_enabled, "echo", ["-e", "tab\\there\\nnew line"]);
+gnu_case!(escapes_stop_at_c, "echo", ["-e", "keep\\cdropped"]);
+gnu_case!(escaped_backslash_before_n, "echo", ["-e", "a\\\\nb"]);
+gnu_case!(escaped_backslash_before_c, "echo", ["-e", "x\\\\cy"]);
+gnu_case!(escaped_backslashes, "echo", ["-e", "a\\\\\\\\b"]);
+gnu_case!(octal_and_hex_escapes, "echo", ["-e", "\\0101\\x42\\x\\q"]);
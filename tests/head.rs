@@ -0,0 +1,98 @@
+mod common;
+
+gnu_case!(default_lines, "head", ["lines.txt"]);
+gnu_case!(lines, "head", ["-n", "3", "lines.txt"]);
+gnu_case!(fewer_lines_than_asked, "head", ["short.txt"]);
+gnu_case!(bytes, "head", ["-c", "12", "lines.txt"]);
+gnu_case!(more_bytes_than_file, "head", ["-c", "100", "short.txt"]);
+gnu_case!(empty_file, "head", ["empty.txt"]);
+gnu_case!(from_stdin, "head", ["-n", "2"], stdin = "short.txt");
+gnu_case!(missing_file, "head", ["missing.txt"]);
+gnu_case!(directory, "head", ["dir"]);
+gnu_case!(many_files, "head", ["-n", "2", "short.txt", "lines.txt"]);
+gnu_case!(no_lines, "head", ["-n", "0", "lines.txt"]);
+gnu_case!(no_bytes, "head", ["-c", "0", "lines.txt"]);
+gnu_case!(many_files_quiet, "head", ["-q", "-n", "2", "short.txt", "lines.txt"]);
+gnu_case!(many_files_bytes, "head", ["-c", "8", "short.txt", "lines.txt", "no_newline.txt"]);
+gnu_case!(single_file_verbose, "head", ["-v", "-n", "1", "short.txt"]);
+gnu_case!(stdin_among_files, "head", ["-n", "1", "short.txt", "-"], stdin = "lines.txt");
+gnu_case!(missing_file_among_others, "head", ["-n", "1", "short.txt", "missing.txt", "lines.txt"]);
+gnu_case!(binary_file, "head", ["binary.bin"]);
+gnu_case!(bytes_with_suffix, "head", ["-c", "1KB", "lines.txt"]);
+gnu_case!(lines_with_suffix, "head", ["-n", "1k", "lines.txt"]);
+gnu_case!(invalid_bytes, "head", ["-c", "1g", "lines.txt"]);
+gnu_case!(too_many_bytes, "head", ["-c", "1Z", "lines.txt"]);
+gnu_case!(obsolete_lines, "head", ["-3", "lines.txt"], golden = lines);
+gnu_case!(obsolete_bytes, "head", ["-12c", "lines.txt"], golden = bytes);
+gnu_case!(obsolete_quiet, "head", ["-2q", "short.txt", "lines.txt"], golden = many_files_quiet);
+gnu_case!(obsolete_invalid_trailing, "head", ["-3x", "lines.txt"]);
+gnu_case!(obsolete_then_lines, "head", ["-5", "-n", "2", "lines.txt"]);
+gnu_case!(last_count_wins, "head", ["-n", "1", "-c", "4", "lines.txt"]);
+gnu_case!(decompress_gzip, "head", ["--decompress", "-c", "12", "lines.txt.gz"], golden = bytes);
+gnu_case!(decompress_zstd, "head", ["--decompress", "-c", "12", "lines.txt.zst"], golden = bytes);
+gnu_case!(decompress_xz, "head", ["--decompress", "-c", "12", "lines.txt.xz"], golden = bytes);
+
+#[test]
+fn large_file_is_mapped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 20_000);
+    let output = common::run(env!("CARGO_BIN_EXE_head"), "head", &["-c", "14", path.to_str().unwrap()], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "line 1\nline 2\n");
+}
+
+#[test]
+fn bytes_from_a_slow_pipe() {
+    let pieces: &[&[u8]] = &[b"ab", b"c", b"\xff\x00d", b"efgh\n", b"ijkl"];
+    let output = common::run_slow(env!("CARGO_BIN_EXE_head"), &["-c", "10"], pieces);
+    assert_eq!(output.stdout, b"abc\xff\x00defgh");
+}
+
+#[test]
+fn bytes_from_a_pipe_ending_early() {
+    let pieces: &[&[u8]] = &[b"ab", b"cd"];
+    let output = common::run_slow(env!("CARGO_BIN_EXE_head"), &["-c", "100"], pieces);
+    assert_eq!(output.stdout, b"abcd");
+}
+
+#[test]
+fn bytes_beyond_any_buffer_are_streamed() {
+    let pieces: &[&[u8]] = &[b"ab", b"cd"];
+    let output = common::run_slow(env!("CARGO_BIN_EXE_head"), &["-c", "8E"], pieces);
+    assert_eq!(output.stdout, b"abcd");
+    assert!(output.status.success());
+}
+
+#[test]
+fn text_goes_on_after_a_binary_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mixed.bin");
+    std::fs::write(&path, b"one\n\xff\xfe two\nthree\n").unwrap();
+    let output = common::run(env!("CARGO_BIN_EXE_head"), "head", &[path.to_str().unwrap()], None);
+    assert_eq!(output.stdout, b"one\n\xff\xfe two\nthree\n");
+}
+
+#[test]
+fn many_binary_lines_from_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("binary_lines");
+    let content: Vec<u8> = (0..100_000).flat_map(|n| if n % 2 == 0 { b"\xff\n".to_vec() } else { b"text\n".to_vec() }).collect();
+    std::fs::write(&path, &content).unwrap();
+    // stdin is read as a stream even from a file, lines that aren't text are put back in front of it
+    let output = common::run(env!("CARGO_BIN_EXE_head"), "head", &["-n", "1000000"], Some(path.to_str().unwrap()));
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout == content, "{} bytes out of {}", output.stdout.len(), content.len());
+}
+
+#[test]
+fn damaged_gzip_input_is_an_error() {
+    let lines = std::fs::read(common::fixtures().join("lines.txt")).unwrap();
+    let output = common::run(env!("CARGO_BIN_EXE_head"), "head", &["--decompress", "-n", "100", "truncated.gz"], None);
+    assert!(!output.stdout.is_empty() && lines.starts_with(&output.stdout), "got {:?}", output.stdout);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "head: error reading 'truncated.gz': incomplete deflate stream\n");
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = common::run(env!("CARGO_BIN_EXE_head"), "head", &["--decompress", "-n", "100", "corrupt.gz"], None);
+    assert_eq!(output.stdout, lines);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "head: error reading 'corrupt.gz': corrupt gzip stream does not have a matching checksum\n");
+    assert_eq!(output.status.code(), Some(1));
+}
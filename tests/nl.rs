@@ -0,0 +1,31 @@
+mod common;
+
+gnu_case!(single_file, "nl", ["lines.txt"]);
+gnu_case!(blank_lines_left_unnumbered, "nl", ["blanks.txt"]);
+gnu_case!(no_trailing_new_line, "nl", ["no_newline.txt"]);
+gnu_case!(many_files, "nl", ["short.txt", "lines.txt"]);
+gnu_case!(from_stdin, "nl", [], stdin = "short.txt");
+gnu_case!(missing_file_among_others, "nl", ["short.txt", "missing.txt", "short.txt"]);
+gnu_case!(sections, "nl", ["pages.txt"]);
+gnu_case!(sections_numbered, "nl", ["-h", "a", "-b", "a", "-f", "t", "pages.txt"]);
+gnu_case!(no_renumber, "nl", ["-p", "-ha", "pages.txt"]);
+gnu_case!(section_delimiter, "nl", ["-d", "X", "pages.txt"]);
+gnu_case!(no_sections, "nl", ["-d", "", "pages.txt"]);
+gnu_case!(numbered_by_regex, "nl", ["-b", "p^b", "pages.txt"]);
+gnu_case!(join_blank_lines, "nl", ["-ba", "-l", "2", "blanks.txt"]);
+gnu_case!(left_justified, "nl", ["-n", "ln", "lines.txt"]);
+gnu_case!(leading_zeros, "nl", ["-n", "rz", "-w", "3", "lines.txt"]);
+gnu_case!(start_and_increment, "nl", ["-v", "-3", "-i", "2", "-n", "rz", "lines.txt"]);
+gnu_case!(width_and_separator, "nl", ["-w", "2", "-s", ": ", "blanks.txt"]);
+gnu_case!(invalid_style, "nl", ["-b", "x", "lines.txt"]);
+gnu_case!(invalid_format, "nl", ["-n", "xx", "lines.txt"]);
+gnu_case!(invalid_start, "nl", ["-v", "one", "lines.txt"]);
+gnu_case!(invalid_width, "nl", ["-w", "0", "lines.txt"]);
+gnu_case!(overflow, "nl", ["-v", "9223372036854775807", "short.txt"]);
+
+#[test]
+fn same_columns_as_cat() {
+    let nl = common::run(env!("CARGO_BIN_EXE_nl"), "nl", &["-ba", "blanks.txt"], None);
+    let cat = common::run(env!("CARGO_BIN_EXE_cat"), "cat", &["-n", "blanks.txt"], None);
+    assert_eq!(nl.stdout, cat.stdout);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+gnu_case!(default_lines, "tac", ["lines.txt"]);
+gnu_case!(from_stdin, "tac", [], stdin = "short.txt");
+gnu_case!(no_trailing_new_line, "tac", ["no_newline.txt"]);
+gnu_case!(empty_file, "tac", ["empty.txt"]);
+gnu_case!(separator, "tac", ["-s", "ne", "lines.txt"]);
+gnu_case!(separator_before, "tac", ["-b", "-s", "ne", "lines.txt"]);
+gnu_case!(new_line_before, "tac", ["-b", "short.txt"]);
+gnu_case!(regex_separator, "tac", ["-r", "-s", "[0-9]+", "lines.txt"]);
+gnu_case!(regex_separator_before, "tac", ["-b", "-r", "-s", "[0-9]+", "lines.txt"]);
+gnu_case!(empty_separator, "tac", ["-s", "", "short.txt"]);
+gnu_case!(invalid_regex, "tac", ["-r", "-s", "[", "short.txt"]);
+gnu_case!(many_files, "tac", ["short.txt", "-", "no_newline.txt"], stdin = "lines.txt");
+gnu_case!(missing_file, "tac", ["short.txt", "missing.txt", "short.txt"]);
+gnu_case!(binary_file, "tac", ["binary.bin"]);
+
+#[test]
+fn large_file_is_read_backwards() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 50_000);
+    let output = common::run(env!("CARGO_BIN_EXE_tac"), "tac", &[path.to_str().unwrap()], None);
+    let expected: String = (1..=50_000).rev().map(|n| format!("line {}\n", n)).collect();
+    assert!(output.stdout == expected.as_bytes(), "output differs");
+}
+
+#[test]
+fn records_longer_than_a_block() {
+    let long = "x".repeat(200_000);
+    let pieces: Vec<Vec<u8>> = vec![format!("{}\nshort\n", long).into_bytes(), format!("{}\n", long).into_bytes()];
+    let pieces: Vec<&[u8]> = pieces.iter().map(|p| p.as_slice()).collect();
+    let output = common::run_slow(env!("CARGO_BIN_EXE_tac"), &[], &pieces);
+    assert!(output.stdout == format!("{}\nshort\n{}\n", long, long).into_bytes(), "output differs");
+}
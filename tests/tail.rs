@@ -0,0 +1,764 @@
+mod common;
+
+gnu_case!(default_lines, "tail", ["lines.txt"]);
+gnu_case!(lines, "tail", ["-n", "3", "lines.txt"]);
+gnu_case!(lines_from_start, "tail", ["-n", "+13", "lines.txt"]);
+gnu_case!(fewer_lines_than_asked, "tail", ["short.txt"]);
+gnu_case!(bytes, "tail", ["-c", "12", "lines.txt"]);
+gnu_case!(bytes_from_start, "tail", ["-c", "+100", "lines.txt"]);
+gnu_case!(no_trailing_new_line, "tail", ["-n", "1", "no_newline.txt"]);
+gnu_case!(empty_file, "tail", ["empty.txt"]);
+gnu_case!(bytes_from_start_stdin, "tail", ["-c", "+100", "-"], stdin = "lines.txt");
+gnu_case!(lines_from_start_stdin, "tail", ["-n", "+13", "-"], stdin = "lines.txt");
+gnu_case!(dash_is_stdin, "tail", ["-n", "2", "-"], stdin = "short.txt");
+gnu_case!(from_stdin, "tail", ["-n", "2"], stdin = "short.txt");
+gnu_case!(missing_file, "tail", ["missing.txt"]);
+gnu_case!(directory, "tail", ["dir"]);
+gnu_case!(many_files, "tail", ["-n", "2", "short.txt", "lines.txt"]);
+gnu_case!(many_files_quiet, "tail", ["-q", "-n", "2", "short.txt", "lines.txt"]);
+gnu_case!(single_file_verbose, "tail", ["-v", "-n", "2", "short.txt"]);
+gnu_case!(missing_file_among_others, "tail", ["-n", "1", "short.txt", "missing.txt", "lines.txt"]);
+gnu_case!(invalid_number, "tail", ["-n", "abc", "short.txt"]);
+gnu_case!(zero_lines, "tail", ["-n", "0", "short.txt", "missing.txt"]);
+gnu_case!(zero_bytes, "tail", ["-c", "0", "short.txt"]);
+gnu_case!(zero_lines_from_start, "tail", ["-n", "+0", "short.txt"]);
+gnu_case!(binary_file, "tail", ["-n", "2", "binary.bin"]);
+gnu_case!(retry_without_follow, "tail", ["--retry", "-n", "1", "short.txt"]);
+gnu_case!(follow_missing_file, "tail", ["-f", "missing.txt"]);
+gnu_case!(follow_name_missing_file, "tail", ["--follow=name", "missing.txt"]);
+gnu_case!(follow_stdin_by_name, "tail", ["-F", "-"], stdin = "short.txt");
+gnu_case!(line_buffered, "tail", ["--line-buffered", "-n", "3", "lines.txt"], golden = lines);
+gnu_case!(unbuffered, "tail", ["--unbuffered", "-n", "3", "lines.txt"], golden = lines);
+gnu_case!(decompress_gzip, "tail", ["--decompress", "-n", "3", "lines.txt.gz"], golden = lines);
+gnu_case!(decompress_zstd, "tail", ["--decompress", "-n", "3", "lines.txt.zst"], golden = lines);
+gnu_case!(decompress_xz, "tail", ["--decompress", "-n", "3", "lines.txt.xz"], golden = lines);
+gnu_case!(decompress_bytes, "tail", ["--decompress", "-c", "12", "lines.txt.gz"], golden = bytes);
+gnu_case!(decompress_bytes_from_start, "tail", ["--decompress", "-c", "+100", "lines.txt.xz"], golden = bytes_from_start);
+gnu_case!(decompress_lines_from_start, "tail", ["--decompress", "-n", "+13", "lines.txt.zst"], golden = lines_from_start);
+gnu_case!(obsolete_lines, "tail", ["-3", "lines.txt"], golden = lines);
+gnu_case!(obsolete_lines_from_start, "tail", ["+13", "lines.txt"], golden = lines_from_start);
+gnu_case!(obsolete_bytes, "tail", ["-12c", "lines.txt"], golden = bytes);
+gnu_case!(obsolete_blocks, "tail", ["-1b", "lines.txt"]);
+gnu_case!(obsolete_stdin, "tail", ["-2", "-"], stdin = "short.txt", golden = dash_is_stdin);
+gnu_case!(obsolete_stdin_after_dashes, "tail", ["-2", "--"], stdin = "short.txt", golden = dash_is_stdin);
+gnu_case!(last_count_wins, "tail", ["-c", "4", "-n", "1", "lines.txt"]);
+
+#[test]
+fn large_file_is_mapped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 20_000);
+    let path = path.to_str().unwrap();
+    let tail = |args: &[&str]| {
+        let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", args, None);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(tail(&["-n", "2", path]), "line 19999\nline 20000\n");
+    assert_eq!(tail(&["-c", "11", path]), "line 20000\n");
+    assert_eq!(tail(&["-n", "+19999", path]), "line 19999\nline 20000\n");
+    assert_eq!(tail(&["-c", "+208884", path]), "line 20000\n");
+}
+
+#[test]
+fn follows_a_mapped_file_that_grows() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 20_000);
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "-n", "1", "large.log"], dir.path());
+    assert!(tail.wait_for("line 20000\n"));
+    common::append(&path, "appended\n");
+    assert!(tail.wait_for("line 20000\nappended\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn reads_a_mapped_file_truncated_under_it() {
+    use rust_core_utils::util::file;
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 20_000);
+    let content = std::fs::read(&path).unwrap();
+
+    let mut large = file::new(path.to_str().unwrap().to_string());
+    large.prepare(0).unwrap();
+    assert!(large.is_mapped());
+    assert_eq!(large.read_line_bytes().unwrap(), b"line 1\n");
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(100_000).unwrap();
+    let mut rest = Vec::new();
+    large.copy_to(&mut rest, None).unwrap();
+    assert_eq!(rest, &content[7..100_000]);
+    assert!(!large.is_mapped());
+
+    std::fs::write(&path, &content).unwrap();
+    let mut large = file::new(path.to_str().unwrap().to_string());
+    large.prepare(0).unwrap();
+    assert!(large.is_mapped());
+    std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(0).unwrap();
+    assert_eq!(large.last_lines(2), (0, vec![]));
+}
+
+#[test]
+fn walking_past_the_end_stops_at_it() {
+    use rust_core_utils::util::file;
+    let short = common::fixtures().join("short.txt");
+    let mut f = file::new(short.to_str().unwrap().to_string());
+    f.prepare(0).unwrap();
+    f.walk_buffer_bytes(6);
+    assert_eq!(f.position(), 6);
+    f.walk_buffer_bytes(1000);
+    assert_eq!(f.position(), 17);
+    assert_eq!(f.read_chunk(10), None);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 20_000);
+    let mut f = file::new(path.to_str().unwrap().to_string());
+    f.prepare(0).unwrap();
+    f.walk_buffer_bytes(1_000_000);
+    assert_eq!(f.position(), 208_894);
+}
+
+#[test]
+fn obsolete_follow() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-2f", "app.log"], dir.path());
+    assert!(tail.wait_for("two\nthree\n"));
+    common::append(&path, "four\n");
+    assert!(tail.wait_for("two\nthree\nfour\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn obsolete_from_start_follows_posix2_version() {
+    let tail = |var: &str, value: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_tail"))
+            .args(["+2", "short.txt"])
+            .current_dir(common::fixtures())
+            .env("LC_ALL", "C")
+            .env(var, value)
+            .output()
+            .unwrap();
+        (String::from_utf8(output.stdout).unwrap(), output.status.code())
+    };
+    // like GNU, POSIXLY_CORRECT alone keeps +NUM a count
+    assert_eq!(tail("POSIXLY_CORRECT", "1"), ("beta\ngamma\n".to_string(), Some(0)));
+    assert_eq!(tail("_POSIX2_VERSION", "199209"), ("beta\ngamma\n".to_string(), Some(0)));
+    assert_eq!(tail("_POSIX2_VERSION", "200112"), ("==> short.txt <==\nalpha\nbeta\ngamma\n".to_string(), Some(1)));
+}
+
+#[test]
+fn procfs_files_are_buffered() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-n", "1", "/proc/self/limits"], None);
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Max realtime timeout"));
+}
+
+#[test]
+fn seeks_far_into_large_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sparse");
+    let file = std::fs::File::create(&path).unwrap();
+    file.set_len(2_000_000_000).unwrap();
+    common::append(&path, "the end\n");
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-c", "+2000000001", path.to_str().unwrap()], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "the end\n");
+}
+
+#[test]
+fn skips_many_lines_from_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 1_000_000);
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_tail"))
+        .args(["-n", "+999999", "-"])
+        .stdin(std::fs::File::open(&path).unwrap())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "line 999999\nline 1000000\n");
+}
+
+#[test]
+fn resumes_where_the_previous_run_stopped() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    let state = dir.path().join("state");
+    std::fs::write(&log, "one\ntwo\nthree\n").unwrap();
+    let tail = || {
+        let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-n", "2", "--resume-state", state.to_str().unwrap(), log.to_str().unwrap()], None);
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(tail(), "two\nthree\n");
+    assert_eq!(tail(), "");
+    common::append(&log, "four\nfi");
+    assert_eq!(tail(), "four\nfi");
+    common::append(&log, "ve\n");
+    assert_eq!(tail(), "ve\n");
+    let saved = std::fs::read_to_string(&state).unwrap();
+    assert!(saved.ends_with(&format!("\t24\t24\t{}\n", log.display())), "state was {:?}", saved);
+}
+
+#[test]
+fn does_not_resume_replaced_or_truncated_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("app.log");
+    let state = dir.path().join("state");
+    std::fs::write(&log, "one\ntwo\nthree\n").unwrap();
+    let tail = || {
+        let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-n", "1", "--resume-state", state.to_str().unwrap(), log.to_str().unwrap()], None);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(tail(), "three\n");
+    std::fs::OpenOptions::new().write(true).open(&log).unwrap().set_len(4).unwrap();
+    assert_eq!(tail(), "one\n");
+    // rotated away rather than removed, the old inode can't be reused for the new file
+    std::fs::rename(&log, dir.path().join("app.log.1")).unwrap();
+    std::fs::write(&log, "rotated\nfile\n").unwrap();
+    assert_eq!(tail(), "file\n");
+}
+
+#[test]
+fn bytes_from_a_slow_pipe() {
+    let pieces: &[&[u8]] = &[b"a", b"bc", b"de", b"f\n", b"g"];
+    let output = common::run_slow(env!("CARGO_BIN_EXE_tail"), &["-c", "+3", "-"], pieces);
+    assert_eq!(output.stdout, b"cdef\ng");
+    let output = common::run_slow(env!("CARGO_BIN_EXE_tail"), &["-c", "4", "-"], pieces);
+    assert_eq!(output.stdout, b"ef\ng");
+}
+
+/// Writes a log of one line a second from 2024-01-01T00:00:00Z on, every
+/// tenth line followed by a line without timestamp as stack traces are
+fn stamped_log(dir: &std::path::Path, lines: i64) -> std::path::PathBuf {
+    let path = dir.join("app.log");
+    let content: String = (0..lines).map(|n| {
+        let line = format!("2024-01-01T{:02}:{:02}:{:02}Z event {}\n", n / 3600, n / 60 % 60, n % 60, n);
+        if n % 10 == 0 { format!("{}  at frame {}\n", line, n) } else { line }
+    }).collect();
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn since_and_until_pick_lines_by_timestamp() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = stamped_log(dir.path(), 50_000);
+    let path = path.to_str().unwrap();
+    let window = ["--since", "2024-01-01T10:00:00Z", "--until", "2024-01-01 10:00:02+00:00"];
+    let expected = "2024-01-01T10:00:00Z event 36000\n  at frame 36000\n2024-01-01T10:00:01Z event 36001\n2024-01-01T10:00:02Z event 36002\n";
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &[&window[..], &[path]].concat(), None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+    // stdin can't be searched, it is read through
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_tail"))
+        .args(window)
+        .stdin(std::fs::File::open(path).unwrap())
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), expected);
+
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-n", "3", "--until", "@1704103201", path], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--since", "2024-01-01T13:53:18Z", path], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "2024-01-01T13:53:18Z event 49998\n2024-01-01T13:53:19Z event 49999\n");
+}
+
+#[test]
+fn since_knows_the_timestamps_of_common_logs() {
+    let dir = tempfile::tempdir().unwrap();
+    let tail = |content: &str, since: &str| {
+        let path = dir.path().join("stamped.log");
+        std::fs::write(&path, content).unwrap();
+        let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--since", since, path.to_str().unwrap()], None);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let syslog = "Jan  1 09:59:59 host sshd[12]: old\nJan  1 10:00:00 host sshd[12]: new\n";
+    assert_eq!(tail(syslog, "Jan  1 10:00:00"), "Jan  1 10:00:00 host sshd[12]: new\n");
+    let clf = "1.2.3.4 - - [01/Jan/2024:09:59:59 +0000] \"GET /old\" 200 1\n1.2.3.4 - bob [01/Jan/2024:11:00:00 +0100] \"GET /new\" 200 1\n";
+    assert_eq!(tail(clf, "2024-01-01T10:00:00Z"), "1.2.3.4 - bob [01/Jan/2024:11:00:00 +0100] \"GET /new\" 200 1\n");
+    let epoch = "1704103199.5 old\n1704103200123 new\n";
+    assert_eq!(tail(epoch, "@1704103200"), "1704103200123 new\n");
+    assert_eq!(tail(epoch, "1h ago"), "");
+}
+
+#[test]
+fn invalid_time_is_an_error() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--since", "yesterday-ish", "short.txt"], None);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tail: invalid time: 'yesterday-ish'\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn until_ends_following() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = stamped_log(dir.path(), 3);
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "--since", "2024-01-01T00:00:02Z", "--until", "2024-01-01T00:00:04Z", "app.log"], dir.path());
+    assert!(tail.wait_for("event 2\n"));
+    common::append(&path, "2024-01-01T00:00:03Z event 3\n  at frame 3\n2024-01-01T00:00:05Z event 5\n2024-01-01T00:00:04Z late\n");
+    assert!(tail.wait_for("event 3\n  at frame 3\n"), "got {:?}", tail.stdout());
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert_eq!(tail.stdout(), "2024-01-01T00:00:02Z event 2\n2024-01-01T00:00:03Z event 3\n  at frame 3\n");
+}
+
+#[test]
+fn match_and_exclude_pick_lines() {
+    let tail = |args: &[&str]| {
+        let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", args, None);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    assert_eq!(tail(&["--match", "line [12]$", "--match", "5", "-n", "+1", "lines.txt"]), "line 1\nline 2\nline 5\nline 15\n");
+    assert_eq!(tail(&["--exclude", "1", "--exclude", "2", "-n", "9", "lines.txt"]), "line 7\nline 8\nline 9\n");
+    assert_eq!(tail(&["--match", "line", "--match", "1", "--color=always", "-n", "1", "lines.txt"]),
+        "\x1b[01;31m\x1b[Kline\x1b[m\x1b[K \x1b[01;31m\x1b[K1\x1b[m\x1b[K5\n");
+    // pipes are no terminal, auto leaves lines as they are
+    assert_eq!(tail(&["--match", "5", "-n", "1", "lines.txt"]), "line 15\n");
+
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--match", "(", "lines.txt"], None);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tail: invalid regular expression: '('\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn matching_lines_keep_their_headers_while_following() {
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app.log");
+    let db = dir.path().join("db.log");
+    std::fs::write(&app, "INFO started\n").unwrap();
+    std::fs::write(&db, "WARN slow query\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "--match", "ERROR|WARN", "app.log", "db.log"], dir.path());
+    assert!(tail.wait_for("==> db.log <==\nWARN slow query\n"), "got {:?}", tail.stdout());
+    common::append(&app, "INFO working\nERROR fail");
+    common::append(&app, "ed\n");
+    assert!(tail.wait_for("ERROR failed\n"), "got {:?}", tail.stdout());
+    // files with nothing matching yet get no header
+    assert_eq!(tail.stdout(), "==> db.log <==\nWARN slow query\n\n==> app.log <==\nERROR failed\n");
+}
+
+fn json_lines(output: &str) -> Vec<serde_json::Value> {
+    output.lines().map(|l| serde_json::from_str(l).unwrap_or_else(|e| panic!("{:?} isn't json: {}", l, e))).collect()
+}
+
+#[test]
+fn json_output_attributes_every_line() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--output=json", "-n", "1", "short.txt", "lines.txt"], None);
+    let lines = json_lines(&String::from_utf8(output.stdout).unwrap());
+    assert_eq!(lines.len(), 2);
+    assert_eq!((&lines[0]["file"], &lines[0]["offset"], &lines[0]["line_number"], &lines[0]["line"]), (&"short.txt".into(), &11.into(), &3.into(), &"gamma".into()));
+    assert_eq!((&lines[1]["file"], &lines[1]["offset"], &lines[1]["line_number"], &lines[1]["line"]), (&"lines.txt".into(), &103.into(), &15.into(), &"line 15".into()));
+    assert!(lines[0]["inode"].is_u64() && lines[0]["received_at"].is_string());
+
+    // lines aren't counted when reading starts at a byte
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--output=json", "-c", "8", "lines.txt"], None);
+    let lines = json_lines(&String::from_utf8(output.stdout).unwrap());
+    assert_eq!((&lines[0]["offset"], &lines[0]["line_number"], &lines[0]["line"]), (&103.into(), &serde_json::Value::Null, &"line 15".into()));
+}
+
+#[test]
+fn json_output_while_following() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "one\ntwo\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "--output=json", "--match", "e", "app.log"], dir.path());
+    assert!(tail.wait_for("\"one\""));
+    common::append(&path, "three\nfo");
+    common::append(&path, "ur\nfive\n");
+    assert!(tail.wait_for("\"five\""), "got {:?}", tail.stdout());
+    let lines = json_lines(&tail.stdout());
+    let seen: Vec<_> = lines.iter().map(|l| (l["line"].as_str().unwrap(), l["offset"].as_u64().unwrap(), l["line_number"].as_u64().unwrap())).collect();
+    assert_eq!(seen, [("one", 0, 1), ("three", 8, 3), ("five", 19, 5)]);
+}
+
+#[test]
+fn max_lines_per_sec_suppresses_floods() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--max-lines-per-sec", "3", "-n", "+1", "lines.txt", "short.txt"], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(),
+        "==> lines.txt <==\nline 1\nline 2\nline 3\n[... 12 lines suppressed ...]\n\n==> short.txt <==\nalpha\nbeta\ngamma\n");
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "--max-lines-per-sec", "2", "app.log"], dir.path());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let flood: String = (1..=10).map(|n| format!("flood {}\n", n)).collect();
+    common::append(&path, &flood);
+    // the marker comes once the second is over, with no need for another line
+    assert!(tail.wait_for("flood 1\nflood 2\n[... 8 lines suppressed ...]\n"), "got {:?}", tail.stdout());
+    common::append(&path, "calm\n");
+    assert!(tail.wait_for("suppressed ...]\ncalm\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn a_flooded_file_does_not_starve_the_others() {
+    use std::io::Write;
+    let dir = tempfile::tempdir().unwrap();
+    let (loud, quiet) = (dir.path().join("a.log"), dir.path().join("b.log"));
+    std::fs::write(&loud, "").unwrap();
+    std::fs::write(&quiet, "").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "--prefix", "a.log", "b.log"], dir.path());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let mut flood = "flood\n".repeat(1_000_000);
+    flood.push_str("last\n");
+    std::fs::OpenOptions::new().append(true).open(&loud).unwrap().write_all(flood.as_bytes()).unwrap();
+    common::append(&quiet, "quiet\n");
+    assert!(tail.wait_for("a.log: last\n"), "never got to the end of a.log");
+    let out = tail.stdout();
+    let (seen, last) = (out.find("b.log: quiet\n"), out.find("a.log: last\n"));
+    assert!(seen.is_some() && seen < last, "b.log came after all of a.log");
+}
+
+#[test]
+fn a_throttled_flood_does_not_starve_the_others() {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    let dir = tempfile::tempdir().unwrap();
+    let (loud, quiet) = (dir.path().join("a.log"), dir.path().join("b.log"));
+    std::fs::write(&loud, "").unwrap();
+    std::fs::write(&quiet, "").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"),
+        &["-f", "-s", "0.1", "--max-lines-per-sec", "5", "a.log", "b.log"], dir.path());
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    // a.log grows faster than the throttle gets through it, for as long as the test runs
+    let stop = std::sync::Arc::new(AtomicBool::new(false));
+    let writer = {
+        let (stop, loud) = (stop.clone(), loud.clone());
+        std::thread::spawn(move || {
+            let mut file = std::fs::OpenOptions::new().append(true).open(&loud).unwrap();
+            let block = "flood\n".repeat(50_000);
+            while !stop.load(Ordering::SeqCst) {
+                file.write_all(block.as_bytes()).unwrap();
+                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+        })
+    };
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    common::append(&quiet, "quiet\n");
+    let through = tail.wait_for("==> b.log <==\nquiet\n");
+    stop.store(true, Ordering::SeqCst);
+    writer.join().unwrap();
+    assert!(through, "got {:?}", tail.stdout());
+}
+
+#[test]
+fn overflow_drops_whole_lines_rather_than_blocking() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = common::large_file(dir.path(), "large.log", 1_000_000);
+    let child = std::process::Command::new(env!("CARGO_BIN_EXE_tail"))
+        .args(["--overflow=drop-newest", "-n", "+1", path.to_str().unwrap()])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    // nothing reads the output for a while, it can't keep up
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let lines = stdout.lines().count();
+    assert!(lines < 1_000_000, "nothing was dropped");
+    assert!(stdout.lines().all(|l| l.starts_with("line ") && l[5..].parse::<u32>().is_ok()), "lines were spliced");
+    assert!(stderr.starts_with("tail: output can't keep up, dropping lines\n"), "got {:?}", stderr);
+    assert!(stderr.ends_with(&format!("tail: {} lines dropped as the output couldn't keep up\n", 1_000_000 - lines)), "got {:?}", stderr);
+}
+
+#[test]
+fn binary_lines_are_kept_as_they_are() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("mixed.bin");
+    let mut content: Vec<u8> = (1..=20_000).flat_map(|n| format!("line {}\n", n).into_bytes()).collect();
+    content.extend_from_slice(b"\xff\xfe not text\nlast\n");
+    std::fs::write(&path, &content).unwrap();
+    let path = path.to_str().unwrap();
+    let tail = |args: &[&str]| common::run(env!("CARGO_BIN_EXE_tail"), "tail", args, None).stdout;
+    assert_eq!(tail(&["-n", "2", path]), b"\xff\xfe not text\nlast\n");
+    assert_eq!(tail(&["-n", "+20001", path]), b"\xff\xfe not text\nlast\n");
+}
+
+#[test]
+fn prefix_replaces_headers() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--prefix", "-n", "1", "short.txt", "dir/../lines.txt"], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "short.txt: gamma\ndir/../lines.txt: line 15\n");
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--prefix=basename,color", "-n", "1", "dir/../lines.txt"], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "\x1b[32mlines.txt:\x1b[0m line 15\n");
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--prefix=size", "short.txt"], None);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tail: invalid prefix format: 'size'\n");
+}
+
+#[test]
+fn prefix_never_splices_lines_of_different_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("a.log"), "").unwrap();
+    std::fs::write(dir.path().join("b.log"), "").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "--prefix", "a.log", "b.log"], dir.path());
+    common::append(&dir.path().join("a.log"), "half a ");
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    common::append(&dir.path().join("b.log"), "whole b\n");
+    assert!(tail.wait_for("b.log: whole b\n"), "got {:?}", tail.stdout());
+    common::append(&dir.path().join("a.log"), "line\n");
+    assert!(tail.wait_for("b.log: whole b\na.log: half a line\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn glob_operands_are_expanded() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-n", "1", "s*.txt", "l*.txt"], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "==> lines.txt <==\nline 15\n\n==> short.txt <==\ngamma\n");
+}
+
+#[test]
+fn watch_dir_picks_up_new_files_and_retires_deleted_ones() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("logs")).unwrap();
+    std::fs::write(dir.path().join("logs").join("old.log"), "old\n").unwrap();
+    let tail = common::Running::start(
+        env!("CARGO_BIN_EXE_tail"),
+        &["-f", "-s", "0.1", "--prefix", "--watch-dir", "logs", "--pattern", "*.log"],
+        dir.path(),
+    );
+    assert!(tail.wait_for("logs/old.log: old\n"), "got {:?}", tail.stdout());
+
+    let new = dir.path().join("logs").join("new.log");
+    std::fs::write(dir.path().join("logs").join("skipped.txt"), "skipped\n").unwrap();
+    std::fs::write(&new, "first\n").unwrap();
+    assert!(tail.wait_for("logs/new.log: first\n"), "got {:?}", tail.stdout());
+
+    std::fs::remove_file(&new).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    std::fs::write(&new, "again\n").unwrap();
+    assert!(tail.wait_for("logs/new.log: again\n"), "got {:?}", tail.stdout());
+    assert!(!tail.stdout().contains("skipped"));
+}
+
+#[test]
+fn follows_thousands_of_files_from_one_thread() {
+    let dir = tempfile::tempdir().unwrap();
+    let files: Vec<String> = (0..3000).map(|n| format!("{}.log", n)).collect();
+    for f in files.iter() {
+        std::fs::write(dir.path().join(f), "").unwrap();
+    }
+    // the last file is read once all the others are open
+    std::fs::write(dir.path().join("2999.log"), "ready\n").unwrap();
+    let mut args = vec!["-f", "-n", "1", "--prefix"];
+    args.extend(files.iter().map(|f| f.as_str()));
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &args, dir.path());
+    assert!(tail.wait_for("2999.log: ready\n"), "got {:?}", tail.stdout());
+
+    for n in [0, 1234, 2999] {
+        common::append(&dir.path().join(format!("{}.log", n)), &format!("hello from {}\n", n));
+        assert!(tail.wait_for(&format!("{}.log: hello from {}\n", n, n)), "got {:?}", tail.stdout());
+    }
+    let status = std::fs::read_to_string(format!("/proc/{}/status", tail.id())).unwrap();
+    let threads: usize = status.lines().find_map(|l| l.strip_prefix("Threads:")).unwrap().trim().parse().unwrap();
+    assert!(threads <= 2, "{} threads running", threads);
+}
+
+#[test]
+fn follow_name_waits_for_files_to_appear() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-F", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for_error("tail: cannot open 'app.log' for reading: No such file or directory\n"));
+
+    std::fs::write(&path, "first\n").unwrap();
+    assert!(tail.wait_for("first\n"), "got {:?}", tail.stdout());
+    assert!(tail.wait_for_error("tail: 'app.log' has appeared;  following new file\n"), "got {:?}", tail.stderr());
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(tail.wait_for_error("tail: 'app.log' has become inaccessible: No such file or directory\n"), "got {:?}", tail.stderr());
+    std::fs::write(&path, "second\n").unwrap();
+    assert!(tail.wait_for("first\nsecond\n"), "got {:?}", tail.stdout());
+    assert_eq!(tail.stderr().matches("has appeared").count(), 2);
+}
+
+#[test]
+fn follow_name_replaced_files_are_followed_from_their_start() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "old\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["--follow=name", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for("old\n"));
+
+    let rotated = dir.path().join("new.log");
+    std::fs::write(&rotated, "new\n").unwrap();
+    std::fs::rename(&rotated, &path).unwrap();
+    assert!(tail.wait_for("old\nnew\n"), "got {:?}", tail.stdout());
+    assert!(tail.wait_for_error("tail: 'app.log' has been replaced;  following new file\n"), "got {:?}", tail.stderr());
+}
+
+/// Writes `content` at the start of the file at `path`, keeping its inode and size
+fn rewrite_in_place(path: &std::path::Path, content: &str) {
+    use std::io::Write;
+    // the modification time has to move on, it is only as fine as the kernel clock tick
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+}
+
+#[test]
+fn remote_magic_numbers_are_unreliable() {
+    use rust_core_utils::util::inotify;
+    // nfs, smb2, cifs and fuse
+    for magic in [0x6969, 0xFE53_4D42, 0xFF53_4D42, 0x6573_5546] {
+        assert!(inotify::is_remote(magic), "{:#x}", magic);
+    }
+    // ext4, xfs, btrfs and tmpfs
+    for magic in [0xEF53, 0x5846_5342, 0x9123_683E, 0x0102_1994] {
+        assert!(!inotify::is_remote(magic), "{:#x}", magic);
+    }
+    let dir = tempfile::tempdir().unwrap();
+    assert!(inotify::filesystem_magic(dir.path()).is_some());
+    assert_eq!(inotify::filesystem_magic(&dir.path().join("missing")), None);
+    assert!(inotify::is_reliable(&dir.path().join("missing")));
+}
+
+#[test]
+fn disabling_inotify_polls_instead() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "old\n").unwrap();
+    let inotify_fds = |tail: &common::Running| {
+        std::fs::read_dir(format!("/proc/{}/fd", tail.id())).unwrap()
+            .filter_map(|fd| std::fs::read_link(fd.unwrap().path()).ok())
+            .filter(|target| target.to_string_lossy() == "anon_inode:inotify")
+            .count()
+    };
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for("old\n"));
+    assert_eq!(inotify_fds(&tail), 1);
+    drop(tail);
+
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "--disable-inotify", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for("old\n"));
+    assert_eq!(inotify_fds(&tail), 0);
+    common::append(&path, "more\n");
+    assert!(tail.wait_for("old\nmore\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn polling_notices_files_rewritten_in_place() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "old\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "---disable-inotify", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for("old\n"));
+
+    rewrite_in_place(&path, "new\n");
+    assert!(tail.wait_for("old\nnew\n"), "got {:?}", tail.stdout());
+    assert!(tail.wait_for_error("tail: app.log: file truncated\n"), "got {:?}", tail.stderr());
+}
+
+#[test]
+fn polling_ignores_files_only_touched() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "old\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-f", "--disable-inotify", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for("old\n"));
+
+    rewrite_in_place(&path, "old\n");
+    common::append(&path, "more\n");
+    assert!(tail.wait_for("old\nmore\n"), "got {:?}", tail.stdout());
+    assert_eq!(tail.stderr(), "");
+}
+
+#[test]
+fn polling_follows_rotated_files_by_name() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "old\n").unwrap();
+    let tail = common::Running::start(env!("CARGO_BIN_EXE_tail"), &["-F", "--disable-inotify", "-s", "0.1", "app.log"], dir.path());
+    assert!(tail.wait_for("old\n"));
+
+    std::fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+    std::fs::write(&path, "new\n").unwrap();
+    assert!(tail.wait_for("old\nnew\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn follow_is_ignored_on_pipes() {
+    let pieces: &[&[u8]] = &[b"one\n", b"two\n"];
+    let output = common::run_slow(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1"], pieces);
+    assert_eq!(output.stdout, b"one\ntwo\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn follows_stdin_redirected_from_a_growing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "first\n").unwrap();
+    let stdin = std::fs::File::open(&path).unwrap();
+    let tail = common::Running::start_with_stdin(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "-"], dir.path(), stdin.into());
+    assert!(tail.wait_for("first\n"));
+    common::append(&path, "second\n");
+    assert!(tail.wait_for("first\nsecond\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn follows_a_fifo_on_stdin_along_with_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let fifo = dir.path().join("fifo");
+    assert!(std::process::Command::new("mkfifo").arg(&fifo).status().unwrap().success());
+    let path = dir.path().join("app.log");
+    std::fs::write(&path, "file\n").unwrap();
+    // opened read write so opening it doesn't wait for tail to show up
+    let mut writer = std::fs::OpenOptions::new().read(true).write(true).open(&fifo).unwrap();
+    let reader = std::fs::File::open(&fifo).unwrap();
+    std::io::Write::write_all(&mut writer, b"from fifo\n").unwrap();
+    drop(writer);
+    let tail = common::Running::start_with_stdin(env!("CARGO_BIN_EXE_tail"), &["-f", "-s", "0.1", "-", "app.log"], dir.path(), reader.into());
+    assert!(tail.wait_for("==> standard input <==\nfrom fifo\n\n==> app.log <==\nfile\n"), "got {:?}", tail.stdout());
+
+    let mut writer = std::fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+    std::io::Write::write_all(&mut writer, b"again\n").unwrap();
+    drop(writer);
+    assert!(tail.wait_for("==> standard input <==\nagain\n"), "got {:?}", tail.stdout());
+    common::append(&path, "more\n");
+    assert!(tail.wait_for("==> app.log <==\nmore\n"), "got {:?}", tail.stdout());
+}
+
+#[test]
+fn line_buffered_lines_show_up_before_stdin_closes() {
+    use std::io::Write;
+    let dir = tempfile::tempdir().unwrap();
+    for option in ["--line-buffered", "--unbuffered"] {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        let tail = common::Running::start_with_stdin(env!("CARGO_BIN_EXE_tail"), &[option, "-n", "+1"], dir.path(), reader.into());
+        writer.write_all(b"first\n").unwrap();
+        assert!(tail.wait_for("first\n"), "{} got {:?}", option, tail.stdout());
+        writer.write_all(b"second\n").unwrap();
+        assert!(tail.wait_for("first\nsecond\n"), "{} got {:?}", option, tail.stdout());
+    }
+}
+
+#[test]
+fn reverse_prints_every_line_last_first() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-r", "short.txt"], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "gamma\nbeta\nalpha\n");
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-r"], Some("short.txt"));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "gamma\nbeta\nalpha\n");
+}
+
+#[test]
+fn reverse_takes_lines_and_bytes() {
+    let run = |args: &[&str]| String::from_utf8(common::run(env!("CARGO_BIN_EXE_tail"), "tail", args, None).stdout).unwrap();
+    assert_eq!(run(&["-r", "-n", "2", "lines.txt"]), "line 15\nline 14\n");
+    assert_eq!(run(&["-2r", "lines.txt"]), "line 15\nline 14\n");
+    assert_eq!(run(&["-r", "-n", "+14", "lines.txt"]), "line 15\nline 14\n");
+    assert_eq!(run(&["-r", "-c", "8", "short.txt"]), "gamma\na\n");
+    assert_eq!(run(&["-r", "-n", "0", "short.txt"]), "");
+    assert_eq!(run(&["-r", "-c", "0", "short.txt"]), "");
+    assert_eq!(run(&["-r", "--decompress", "-n", "1", "short.txt", "lines.txt.gz"]), "==> short.txt <==\ngamma\n\n==> lines.txt.gz <==\nline 15\n");
+}
+
+#[test]
+fn reverse_doesnt_follow() {
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["-r", "-f", "short.txt"], None);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tail: -r can't be used with --follow\n");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn damaged_gzip_input_is_an_error() {
+    let lines = std::fs::read(common::fixtures().join("lines.txt")).unwrap();
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--decompress", "-n", "100", "truncated.gz"], None);
+    assert!(!output.stdout.is_empty() && lines.starts_with(&output.stdout), "got {:?}", output.stdout);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tail: error reading 'truncated.gz': incomplete deflate stream\n");
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = common::run(env!("CARGO_BIN_EXE_tail"), "tail", &["--decompress", "-n", "100", "corrupt.gz"], None);
+    assert_eq!(output.stdout, lines);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tail: error reading 'corrupt.gz': corrupt gzip stream does not have a matching checksum\n");
+    assert_eq!(output.status.code(), Some(1));
+}
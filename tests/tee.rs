@@ -0,0 +1,82 @@
+mod common;
+
+use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
+use std::process::{Command, ExitStatus, Output, Stdio};
+
+gnu_case!(to_stdout, "tee", [], stdin = "lines.txt");
+gnu_case!(missing_directory, "tee", ["nodir/out.txt"], stdin = "short.txt");
+gnu_case!(full_device, "tee", ["/dev/full"], stdin = "short.txt");
+gnu_case!(full_device_among_files, "tee", ["-p", "/dev/full", "/dev/null"], stdin = "short.txt");
+
+#[test]
+fn writes_every_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let (one, two) = (dir.path().join("one"), dir.path().join("two"));
+    std::fs::write(&two, "kept?\n").unwrap();
+    let args = [one.to_str().unwrap(), two.to_str().unwrap()];
+    let output = common::run(env!("CARGO_BIN_EXE_tee"), "tee", &args, Some("short.txt"));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "alpha\nbeta\ngamma\n");
+    assert_eq!(std::fs::read_to_string(&one).unwrap(), "alpha\nbeta\ngamma\n");
+    assert_eq!(std::fs::read_to_string(&two).unwrap(), "alpha\nbeta\ngamma\n");
+}
+
+#[test]
+fn appends() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("log");
+    std::fs::write(&path, "kept\n").unwrap();
+    common::run(env!("CARGO_BIN_EXE_tee"), "tee", &["-a", path.to_str().unwrap()], Some("short.txt"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "kept\nalpha\nbeta\ngamma\n");
+}
+
+/// Runs tee with its stdout a pipe closed right away, `file` still gets everything
+fn closed_stdout(args: &[&str], file: &std::path::Path) -> (ExitStatus, Output) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tee"))
+        .args(args)
+        .arg(file)
+        .env("LC_ALL", "C")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let mut stdin = child.stdin.take().unwrap();
+    let line = "0123456789abcdef\n".repeat(4096);
+    for _ in 0..64 {
+        if stdin.write_all(line.as_bytes()).is_err() {
+            break;
+        }
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    (output.status, output)
+}
+
+#[test]
+fn broken_pipes_kill_tee_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let (status, _) = closed_stdout(&[], &dir.path().join("out"));
+    assert_eq!(status.signal(), Some(libc::SIGPIPE));
+}
+
+#[test]
+fn broken_pipes_are_left_quietly_with_p() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out");
+    let (status, output) = closed_stdout(&["-p"], &path);
+    assert_eq!(status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "");
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 64 * 4096 * 17);
+}
+
+#[test]
+fn broken_pipes_are_diagnosed_with_warn() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out");
+    let (status, output) = closed_stdout(&["--output-error=warn"], &path);
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "tee: 'standard output': Broken pipe\n");
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 64 * 4096 * 17);
+}
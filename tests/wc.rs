@@ -0,0 +1,69 @@
+mod common;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+gnu_case!(default_counts, "wc", ["lines.txt"]);
+gnu_case!(lines, "wc", ["-l", "lines.txt"]);
+gnu_case!(words, "wc", ["-w", "blanks.txt"]);
+gnu_case!(chars, "wc", ["-m", "binary.bin"]);
+gnu_case!(max_line_length, "wc", ["-L", "lines.txt"]);
+gnu_case!(every_count, "wc", ["-clmwL", "binary.bin", "no_newline.txt"]);
+gnu_case!(empty_file, "wc", ["empty.txt"]);
+gnu_case!(from_stdin, "wc", [], stdin = "lines.txt");
+gnu_case!(bytes_from_stdin, "wc", ["-c"], stdin = "lines.txt");
+gnu_case!(dash_among_files, "wc", ["lines.txt", "-"], stdin = "short.txt");
+gnu_case!(many_files, "wc", ["lines.txt", "short.txt"]);
+gnu_case!(many_files_lines, "wc", ["-l", "lines.txt", "short.txt"]);
+gnu_case!(binary_file, "wc", ["binary.bin"]);
+gnu_case!(missing_file, "wc", ["missing.txt"]);
+gnu_case!(directory, "wc", ["dir"]);
+gnu_case!(directory_among_files, "wc", ["dir", "short.txt"]);
+gnu_case!(missing_among_others, "wc", ["-l", "empty.txt", "dir", "missing.txt"]);
+gnu_case!(directory_on_stdin, "wc", [], stdin = "dir");
+gnu_case!(directory_on_stdin_among_files, "wc", ["-l", "-", "short.txt"], stdin = "dir");
+gnu_case!(files0_from, "wc", ["--files0-from=files0"]);
+gnu_case!(files0_from_stdin, "wc", ["--files0-from=-"], stdin = "files0");
+gnu_case!(files0_from_with_operand, "wc", ["--files0-from=files0", "short.txt"]);
+gnu_case!(files0_from_missing, "wc", ["--files0-from=missing.txt"]);
+
+#[test]
+fn procfs_files_are_read_through() {
+    let size = std::fs::read("/proc/version").unwrap().len();
+    let output = common::run(env!("CARGO_BIN_EXE_wc"), "wc", &["-c", "/proc/version"], None);
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), format!("{} /proc/version\n", size));
+}
+
+/// Runs wc in a utf-8 locale, feeding it `pieces` one write at a time
+fn utf8_counts(args: &[&str], pieces: &[&[u8]], posixly_correct: bool) -> String {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_wc"));
+    cmd.args(args).env("LC_ALL", "C.UTF-8").env_remove("POSIXLY_CORRECT");
+    if posixly_correct {
+        cmd.env("POSIXLY_CORRECT", "1");
+    }
+    let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for piece in pieces {
+        stdin.write_all(piece).unwrap();
+        stdin.flush().unwrap();
+        thread::sleep(Duration::from_millis(30));
+    }
+    drop(stdin);
+    String::from_utf8(child.wait_with_output().unwrap().stdout).unwrap()
+}
+
+// the expected counts are gnu's
+#[test]
+fn multibyte_characters_in_utf8_locales() {
+    let text: &[u8] = b"h\xc3\xa9llo w\xe2\x80\x8borld\t\xe4\xb8\xad\xe6\x96\x87\xc2\xa0x\n\xff";
+    assert_eq!(utf8_counts(&["-lwmcL"], &[text], false), "      1       4      18      27      22\n");
+    assert_eq!(utf8_counts(&["-w"], &[text], true), "3\n");
+}
+
+#[test]
+fn characters_split_between_reads() {
+    let pieces: &[&[u8]] = &[b"\xc3", b"\xa9\xe4\xb8", b"\xad\n\xe6", b"\x96\x87"];
+    assert_eq!(utf8_counts(&["-m"], pieces, false), "4\n");
+}
@@ -0,0 +1,32 @@
+mod common;
+
+// zcat runs cat --decompress, so it has to print what gnu cat prints for the plain files
+
+#[test]
+fn decompress_gzip() {
+    common::check("cat", env!("CARGO_BIN_EXE_zcat"), "zcat", "single_file", &["lines.txt.gz"], None);
+}
+
+#[test]
+fn decompress_many_formats() {
+    common::check("cat", env!("CARGO_BIN_EXE_zcat"), "zcat", "many_files", &["short.txt", "lines.txt.xz", "short.txt"], None);
+}
+
+#[test]
+fn decompress_stdin() {
+    common::check("cat", env!("CARGO_BIN_EXE_zcat"), "zcat", "from_stdin", &[], Some("short.txt"));
+}
+
+#[test]
+fn damaged_gzip_input_is_an_error() {
+    let lines = std::fs::read(common::fixtures().join("lines.txt")).unwrap();
+    let output = common::run(env!("CARGO_BIN_EXE_zcat"), "zcat", &["truncated.gz"], None);
+    assert!(!output.stdout.is_empty() && lines.starts_with(&output.stdout), "got {:?}", output.stdout);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "cat: truncated.gz: incomplete deflate stream\n");
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = common::run(env!("CARGO_BIN_EXE_zcat"), "zcat", &["corrupt.gz"], None);
+    assert_eq!(output.stdout, lines);
+    assert_eq!(String::from_utf8(output.stderr).unwrap(), "cat: corrupt.gz: corrupt gzip stream does not have a matching checksum\n");
+    assert_eq!(output.status.code(), Some(1));
+}